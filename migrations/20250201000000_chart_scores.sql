@@ -0,0 +1,11 @@
+CREATE TABLE chart_scores (
+    id INTEGER PRIMARY KEY NOT NULL,
+    type INTEGER NOT NULL,
+    weighted_rating REAL NOT NULL,
+    num_votes INTEGER NOT NULL,
+
+    FOREIGN KEY (id) REFERENCES titles(id)
+) STRICT;
+
+CREATE INDEX idx_chart_scores_weighted_rating ON chart_scores(type, weighted_rating DESC);
+CREATE INDEX idx_chart_scores_num_votes ON chart_scores(type, num_votes DESC);
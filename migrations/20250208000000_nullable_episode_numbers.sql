@@ -0,0 +1,18 @@
+-- specials and unnumbered episodes have no season and/or episode number
+CREATE TABLE episodes_new (
+    id INTEGER PRIMARY KEY NOT NULL,
+    parent_id INTEGER NOT NULL,
+    season_number INTEGER,
+    episode_number INTEGER,
+
+    FOREIGN KEY (id) REFERENCES titles(id),
+    FOREIGN KEY (parent_id) REFERENCES titles(id)
+) STRICT;
+
+INSERT INTO episodes_new (id, parent_id, season_number, episode_number)
+SELECT id, parent_id, season_number, episode_number FROM episodes;
+
+DROP TABLE episodes;
+ALTER TABLE episodes_new RENAME TO episodes;
+
+CREATE INDEX idx_episode_parent_id ON episodes(parent_id);
@@ -0,0 +1,8 @@
+-- filled by the importers, see normalize_title()
+ALTER TABLE titles ADD COLUMN normalized_primary_title TEXT;
+ALTER TABLE titles ADD COLUMN normalized_original_title TEXT;
+ALTER TABLE akas ADD COLUMN normalized_title TEXT;
+
+CREATE INDEX idx_titles_normalized_primary_title ON titles(normalized_primary_title);
+CREATE INDEX idx_titles_normalized_original_title ON titles(normalized_original_title);
+CREATE INDEX idx_akas_normalized_title ON akas(normalized_title);
@@ -0,0 +1,45 @@
+-- filled by the basics importer, titles.genres is kept for display
+CREATE TABLE title_genres (
+    title_id INTEGER NOT NULL,
+    genre_id INTEGER NOT NULL,
+    PRIMARY KEY (title_id, genre_id)
+) WITHOUT ROWID;
+
+CREATE INDEX idx_title_genres_genre_id ON title_genres(genre_id, title_id);
+
+-- ids match the Genre enum
+WITH genres (id, name) AS (VALUES
+    (0, 'Action'),
+    (1, 'Adult'),
+    (2, 'Adventure'),
+    (3, 'Animation'),
+    (4, 'Biography'),
+    (5, 'Comedy'),
+    (6, 'Crime'),
+    (7, 'Documentary'),
+    (8, 'Drama'),
+    (9, 'Family'),
+    (10, 'Fantasy'),
+    (11, 'Film-Noir'),
+    (12, 'Game-Show'),
+    (13, 'History'),
+    (14, 'Horror'),
+    (15, 'Music'),
+    (16, 'Musical'),
+    (17, 'Mystery'),
+    (18, 'News'),
+    (19, 'Reality-TV'),
+    (20, 'Romance'),
+    (21, 'Sci-Fi'),
+    (22, 'Short'),
+    (23, 'Sport'),
+    (24, 'Talk-Show'),
+    (25, 'Thriller'),
+    (26, 'War'),
+    (27, 'Western')
+)
+INSERT INTO title_genres (title_id, genre_id)
+SELECT t.id, g.id
+FROM titles t, json_each('["' || replace(t.genres, ',', '","') || '"]') j
+JOIN genres g ON g.name = j.value
+WHERE t.genres IS NOT NULL;
@@ -0,0 +1,5 @@
+-- set to the ID of the import that last wrote each row, so rows missing from a new dataset version can be removed
+ALTER TABLE titles ADD COLUMN import_id INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE akas ADD COLUMN import_id INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE episodes ADD COLUMN import_id INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE ratings ADD COLUMN import_id INTEGER NOT NULL DEFAULT 0;
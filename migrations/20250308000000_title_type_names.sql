@@ -0,0 +1,5 @@
+-- IMDb's titleType for titles of a type hubble doesn't know yet, which are stored with kind OTHER (11)
+ALTER TABLE titles ADD COLUMN type_name TEXT;
+
+-- small, only titles of unknown types are in it
+CREATE INDEX idx_titles_type_name ON titles(type_name) WHERE type_name IS NOT NULL;
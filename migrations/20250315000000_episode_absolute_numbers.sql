@@ -0,0 +1,10 @@
+-- position across all seasons in airing order, recomputed after every import of episodes or basics
+ALTER TABLE episodes ADD COLUMN absolute_number INTEGER;
+
+UPDATE episodes SET absolute_number = numbered.absolute_number
+FROM (
+    SELECT id, ROW_NUMBER() OVER (PARTITION BY parent_id ORDER BY season_number, episode_number, id) AS absolute_number
+    FROM episodes
+    WHERE season_number >= 1 AND episode_number IS NOT NULL
+) AS numbered
+WHERE episodes.id = numbered.id;
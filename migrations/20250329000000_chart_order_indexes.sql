@@ -0,0 +1,3 @@
+-- The chart indexes lead with the title type, so charts over every kind had to sort all scores
+CREATE INDEX idx_chart_scores_weighted_rating_all ON chart_scores(weighted_rating DESC);
+CREATE INDEX idx_chart_scores_num_votes_all ON chart_scores(num_votes DESC);
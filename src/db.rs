@@ -0,0 +1,320 @@
+use anyhow::{Context, Result};
+use sqlx::{
+    sqlite::{
+        SqliteAutoVacuum, SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions,
+        SqliteSynchronous,
+    },
+    SqlitePool,
+};
+use std::{
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, RwLock,
+    },
+    time::Duration,
+};
+
+/// The live database, which is replaced as a whole when a sync built a new version.
+///
+/// Each version is a separate file next to the one in `DATABASE_URL`, like `hubble-1735689600000.db`,
+/// and `hubble.db.current` holds the name of the live one. Before the first sync swaps in a new version,
+/// the `DATABASE_URL` file itself is live.
+pub struct Database {
+    options: SqliteConnectOptions,
+    live: RwLock<Arc<Version>>,
+}
+
+/// A database version and its pool. Once a newer version was swapped in, its files are deleted as soon as
+/// the last `Arc` of it is dropped, i.e. when the requests that were still using it finished.
+pub struct Version {
+    pub path: PathBuf,
+    pub pool: SqlitePool,
+    replaced: AtomicBool,
+}
+
+impl Version {
+    fn new(path: PathBuf, pool: SqlitePool) -> Arc<Self> {
+        Arc::new(Self {
+            path,
+            pool,
+            replaced: AtomicBool::new(false),
+        })
+    }
+}
+
+impl Drop for Version {
+    fn drop(&mut self) {
+        // the live version is dropped on shutdown too, and has to stay
+        if !self.replaced.load(Ordering::Acquire) {
+            return;
+        }
+
+        let pool = self.pool.clone();
+        let path = std::mem::take(&mut self.path);
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            return;
+        };
+        runtime.spawn(async move {
+            pool.close().await;
+            match remove_database_files(&path) {
+                Ok(()) => tracing::info!("removed old database {path:?}"),
+                Err(error) => tracing::error!("failed to remove old database {path:?}: {error}"),
+            }
+        });
+    }
+}
+
+/// A new database version being built from a copy of the live one
+pub struct Build {
+    pub path: PathBuf,
+    pub pool: SqlitePool,
+}
+
+impl Database {
+    pub async fn open(database_url: &str) -> Result<Self> {
+        // https://briandouglas.ie/sqlite-defaults/
+        let options = SqliteConnectOptions::from_str(database_url)
+            .context("Failed to parse SQLite path")?
+            .journal_mode(SqliteJournalMode::Wal)
+            .synchronous(SqliteSynchronous::Normal)
+            .busy_timeout(Duration::from_secs(5))
+            .foreign_keys(true)
+            .auto_vacuum(SqliteAutoVacuum::Incremental)
+            .pragma("cache_size", "-20000")
+            .pragma("temp_store", "MEMORY")
+            .pragma("mmap_size", "2147483648")
+            .page_size(8192);
+
+        let base_path = options.get_filename().to_path_buf();
+        let path = match std::fs::read_to_string(get_pointer_path(&base_path)) {
+            Ok(name) => base_path.with_file_name(name.trim()),
+            Err(_) => base_path.clone(),
+        };
+
+        remove_abandoned_builds(&base_path, &path)?;
+
+        tracing::info!("opening database {path:?}");
+        let pool = connect(&options, &path).await?;
+        Ok(Self {
+            options,
+            live: RwLock::new(Version::new(path, pool)),
+        })
+    }
+
+    /// The live version. Hold on to it for the duration of a request, so every query sees the same
+    /// version even if a new one is swapped in meanwhile, and its files aren't deleted until it's done.
+    pub fn current(&self) -> Arc<Version> {
+        self.live.read().unwrap().clone()
+    }
+
+    /// Copy the live database to a new file to import into.
+    pub async fn create_build(&self) -> Result<Build> {
+        let live = self.current();
+        let base_path = self.options.get_filename();
+        let stem = base_path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or("hubble".to_string());
+        let path = base_path.with_file_name(format!(
+            "{stem}-{}.db",
+            chrono::Utc::now().timestamp_millis()
+        ));
+
+        tracing::info!("copying {:?} to {path:?} for a new build", live.path);
+        // VACUUM INTO writes a consistent copy even while the live database is being read
+        sqlx::query("VACUUM INTO ?")
+            .bind(path.to_string_lossy())
+            .execute(&live.pool)
+            .await?;
+
+        let pool = connect(&self.options, &path).await?;
+        Ok(Build { path, pool })
+    }
+
+    /// Check the build and make it the live version. The old version is deleted once the requests
+    /// that started before the swap finished, see `Version`.
+    pub async fn swap(&self, build: Build) -> Result<()> {
+        if let Err(error) = validate(&build.pool).await {
+            self.discard(build).await?;
+            return Err(error.context("new database version failed validation"));
+        }
+
+        let base_path = self.options.get_filename();
+        let pointer_path = get_pointer_path(base_path);
+        let name = build
+            .path
+            .file_name()
+            .context("build has no file name")?
+            .to_string_lossy()
+            .into_owned();
+        // write and rename so the pointer is never half written
+        let tmp_path = pointer_path.with_extension("current.tmp");
+        std::fs::write(&tmp_path, name)?;
+        std::fs::rename(&tmp_path, &pointer_path)?;
+
+        tracing::info!("swapping in database {:?}", build.path);
+        let old = std::mem::replace(
+            &mut *self.live.write().unwrap(),
+            Version::new(build.path, build.pool),
+        );
+        old.replaced.store(true, Ordering::Release);
+        Ok(())
+    }
+
+    /// Delete a build that won't be used.
+    pub async fn discard(&self, build: Build) -> Result<()> {
+        build.pool.close().await;
+        remove_database_files(&build.path)
+    }
+}
+
+async fn connect(options: &SqliteConnectOptions, path: &Path) -> Result<SqlitePool> {
+    let pool = SqlitePoolOptions::new()
+        .max_connections(2) // avoid locking
+        .acquire_timeout(Duration::from_secs(30))
+        .connect_with(options.clone().filename(path).create_if_missing(true))
+        .await
+        .with_context(|| format!("Failed to connect to SQLite at {path:?}"))?;
+
+    sqlx::migrate!("./migrations")
+        .run(&pool)
+        .await
+        .context("Failed to run migrations")?;
+
+    Ok(pool)
+}
+
+/// Sanity checks before a build goes live. `quick_check` skips the slow index checks of `integrity_check`.
+async fn validate(pool: &SqlitePool) -> Result<()> {
+    let quick_check: String = sqlx::query_scalar("PRAGMA quick_check")
+        .fetch_one(pool)
+        .await?;
+    if quick_check != "ok" {
+        anyhow::bail!("quick_check failed: {quick_check}");
+    }
+
+    let violations = sqlx::query("PRAGMA foreign_key_check")
+        .fetch_all(pool)
+        .await?;
+    if !violations.is_empty() {
+        anyhow::bail!("{} foreign key violations", violations.len());
+    }
+
+    let has_titles = sqlx::query_scalar!("SELECT EXISTS (SELECT 1 FROM titles)")
+        .fetch_one(pool)
+        .await?
+        == 1;
+    if !has_titles {
+        anyhow::bail!("titles is empty");
+    }
+
+    Ok(())
+}
+
+fn get_pointer_path(base_path: &Path) -> PathBuf {
+    let mut pointer_path = base_path.as_os_str().to_owned();
+    pointer_path.push(".current");
+    PathBuf::from(pointer_path)
+}
+
+/// Delete versions other than the live one, left behind by a build that was interrupted
+/// or an old version that was still waiting to be removed when the process exited.
+fn remove_abandoned_builds(base_path: &Path, live_path: &Path) -> Result<()> {
+    let (Some(dir), Some(stem)) = (base_path.parent(), base_path.file_stem()) else {
+        return Ok(());
+    };
+    let prefix = format!("{}-", stem.to_string_lossy());
+    let dir = if dir.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        dir
+    };
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let is_version = name.starts_with(&prefix)
+            && name.ends_with(".db")
+            && name[prefix.len()..name.len() - 3]
+                .chars()
+                .all(|c| c.is_ascii_digit());
+        if is_version && path.file_name() != live_path.file_name() {
+            tracing::info!("removing abandoned database {path:?}");
+            remove_database_files(&path)?;
+        }
+    }
+    Ok(())
+}
+
+fn remove_database_files(path: &Path) -> Result<()> {
+    for suffix in ["", "-wal", "-shm"] {
+        let mut file = path.as_os_str().to_owned();
+        file.push(suffix);
+        match std::fs::remove_file(&file) {
+            Err(error) if error.kind() != std::io::ErrorKind::NotFound => return Err(error.into()),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn build_with_title(db: &Database) -> Build {
+        let build = db.create_build().await.unwrap();
+        sqlx::query(
+            "INSERT OR REPLACE INTO titles (id, type, primary_title, is_adult) VALUES (1, 0, 'Title', 0)",
+        )
+        .execute(&build.pool)
+        .await
+        .unwrap();
+        build
+    }
+
+    /// Wait for the cleanup task spawned when a replaced version is dropped.
+    async fn wait_for_removal(path: &Path) -> bool {
+        for _ in 0..100 {
+            if !path.exists() {
+                return true;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        false
+    }
+
+    #[tokio::test]
+    async fn keeps_replaced_version_until_released() {
+        let dir = tempfile::tempdir().unwrap();
+        let url = format!("sqlite://{}", dir.path().join("hubble.db").display());
+        let db = Database::open(&url).await.unwrap();
+
+        let build = build_with_title(&db).await;
+        db.swap(build).await.unwrap();
+        let first = db.current();
+
+        // a request that started before the next swap keeps using its version
+        let build = build_with_title(&db).await;
+        let second_path = build.path.clone();
+        db.swap(build).await.unwrap();
+        assert!(first.path.exists());
+        sqlx::query("SELECT COUNT(*) FROM titles")
+            .execute(&first.pool)
+            .await
+            .unwrap();
+
+        let first_path = first.path.clone();
+        drop(first);
+        assert!(wait_for_removal(&first_path).await);
+
+        // the live version stays when the database is dropped, like on shutdown
+        drop(db);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(second_path.exists());
+    }
+}
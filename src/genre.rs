@@ -0,0 +1,115 @@
+use async_graphql::Enum;
+
+/// The genres IMDb assigns to titles. The discriminants are the `genre_id`s in `title_genres`.
+#[derive(Enum, Copy, Clone, Eq, PartialEq, Debug, sqlx::Type)]
+#[repr(i64)]
+pub enum Genre {
+    Action = 0,
+    Adult = 1,
+    Adventure = 2,
+    Animation = 3,
+    Biography = 4,
+    Comedy = 5,
+    Crime = 6,
+    Documentary = 7,
+    Drama = 8,
+    Family = 9,
+    Fantasy = 10,
+    FilmNoir = 11,
+    GameShow = 12,
+    History = 13,
+    Horror = 14,
+    Music = 15,
+    Musical = 16,
+    Mystery = 17,
+    News = 18,
+    RealityTv = 19,
+    Romance = 20,
+    SciFi = 21,
+    Short = 22,
+    Sport = 23,
+    TalkShow = 24,
+    Thriller = 25,
+    War = 26,
+    Western = 27,
+}
+
+impl Genre {
+    pub fn from_imdb(value: &str) -> Option<Self> {
+        Some(match value {
+            "Action" => Genre::Action,
+            "Adult" => Genre::Adult,
+            "Adventure" => Genre::Adventure,
+            "Animation" => Genre::Animation,
+            "Biography" => Genre::Biography,
+            "Comedy" => Genre::Comedy,
+            "Crime" => Genre::Crime,
+            "Documentary" => Genre::Documentary,
+            "Drama" => Genre::Drama,
+            "Family" => Genre::Family,
+            "Fantasy" => Genre::Fantasy,
+            "Film-Noir" => Genre::FilmNoir,
+            "Game-Show" => Genre::GameShow,
+            "History" => Genre::History,
+            "Horror" => Genre::Horror,
+            "Music" => Genre::Music,
+            "Musical" => Genre::Musical,
+            "Mystery" => Genre::Mystery,
+            "News" => Genre::News,
+            "Reality-TV" => Genre::RealityTv,
+            "Romance" => Genre::Romance,
+            "Sci-Fi" => Genre::SciFi,
+            "Short" => Genre::Short,
+            "Sport" => Genre::Sport,
+            "Talk-Show" => Genre::TalkShow,
+            "Thriller" => Genre::Thriller,
+            "War" => Genre::War,
+            "Western" => Genre::Western,
+            _ => return None,
+        })
+    }
+
+    /// Parse IMDb's comma-separated genre list, skipping genres hubble doesn't know about.
+    /// The basics importer warns about those.
+    pub fn parse_list(value: &str) -> Vec<Self> {
+        value.split(',').filter_map(Genre::from_imdb).collect()
+    }
+}
+
+impl TryFrom<i64> for Genre {
+    type Error = String;
+
+    fn try_from(genre: i64) -> Result<Self, Self::Error> {
+        Ok(match genre {
+            0 => Genre::Action,
+            1 => Genre::Adult,
+            2 => Genre::Adventure,
+            3 => Genre::Animation,
+            4 => Genre::Biography,
+            5 => Genre::Comedy,
+            6 => Genre::Crime,
+            7 => Genre::Documentary,
+            8 => Genre::Drama,
+            9 => Genre::Family,
+            10 => Genre::Fantasy,
+            11 => Genre::FilmNoir,
+            12 => Genre::GameShow,
+            13 => Genre::History,
+            14 => Genre::Horror,
+            15 => Genre::Music,
+            16 => Genre::Musical,
+            17 => Genre::Mystery,
+            18 => Genre::News,
+            19 => Genre::RealityTv,
+            20 => Genre::Romance,
+            21 => Genre::SciFi,
+            22 => Genre::Short,
+            23 => Genre::Sport,
+            24 => Genre::TalkShow,
+            25 => Genre::Thriller,
+            26 => Genre::War,
+            27 => Genre::Western,
+            _ => return Err(format!("unknown genre id {genre}")),
+        })
+    }
+}
@@ -0,0 +1,120 @@
+use crate::id::{Id, NodeId};
+use async_graphql::{ComplexObject, Context, Enum, Result, SimpleObject, ID};
+use itertools::Itertools;
+use sqlx::{query_as, SqlitePool};
+
+#[derive(SimpleObject, Clone)]
+#[graphql(complex)]
+pub struct Aka {
+    #[graphql(skip)]
+    pub title_id: Id,
+    pub ordering: i64,
+    pub title: String,
+    pub region: Option<String>,
+    pub language: Option<String>,
+    pub types: Vec<AkaType>,
+    /// Raw values for any entries in `types` that came back as `OTHER`
+    pub other_types: Vec<String>,
+    pub attributes: Vec<String>,
+    pub is_original_title: bool,
+}
+
+#[ComplexObject]
+impl Aka {
+    pub async fn id(&self, _ctx: &Context<'_>) -> Result<ID> {
+        Ok(NodeId::Aka(self.title_id, self.ordering).into())
+    }
+}
+
+impl Aka {
+    /// Build an aka from a row, splitting the comma separated `types` and `attributes` columns
+    pub fn from_row(title_id: Id, row: AkaRow) -> Self {
+        let raw_types = row
+            .types
+            .map(|t| t.split(",").map(|s| s.to_string()).collect_vec())
+            .unwrap_or_default();
+
+        Aka {
+            title_id,
+            ordering: row.ordering,
+            title: row.title,
+            region: row.region,
+            language: row.language,
+            attributes: row
+                .attributes
+                .map(|a| a.split(",").map(|s| s.to_string()).collect())
+                .unwrap_or_default(),
+            types: raw_types.iter().map(|t| AkaType::from_imdb(t)).collect(),
+            other_types: raw_types
+                .into_iter()
+                .filter(|t| AkaType::from_imdb(t) == AkaType::Other)
+                .collect(),
+            is_original_title: row.is_original_title,
+        }
+    }
+
+    pub fn has_type(&self, kind: AkaType) -> bool {
+        self.types.contains(&kind)
+    }
+}
+
+pub struct AkaRow {
+    pub ordering: i64,
+    pub title: String,
+    pub region: Option<String>,
+    pub language: Option<String>,
+    pub types: Option<String>,
+    pub attributes: Option<String>,
+    pub is_original_title: bool,
+}
+
+pub async fn get_aka(
+    pool: &SqlitePool,
+    title_id: Id,
+    ordering: i64,
+) -> Result<Option<Aka>, sqlx::Error> {
+    let id = title_id.get();
+    let aka = query_as!(
+        AkaRow,
+        "SELECT 
+        ordering, title, region, language, types, attributes, is_original_title AS \"is_original_title: bool\"
+        FROM akas
+        WHERE id = ? AND ordering = ?",
+        id,
+        ordering
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(aka.map(|row| Aka::from_row(title_id, row)))
+}
+
+#[derive(Enum, Copy, Clone, Eq, PartialEq, Debug)]
+pub enum AkaType {
+    Original,
+    ImdbDisplay,
+    Alternative,
+    Dvd,
+    Festival,
+    Tv,
+    Video,
+    Working,
+    /// A type IMDb added after this list was written, see `Aka.otherTypes`
+    Other,
+}
+
+impl AkaType {
+    pub fn from_imdb(value: &str) -> Self {
+        match value {
+            "original" => AkaType::Original,
+            "imdbDisplay" => AkaType::ImdbDisplay,
+            "alternative" => AkaType::Alternative,
+            "dvd" => AkaType::Dvd,
+            "festival" => AkaType::Festival,
+            "tv" => AkaType::Tv,
+            "video" => AkaType::Video,
+            "working" => AkaType::Working,
+            _ => AkaType::Other,
+        }
+    }
+}
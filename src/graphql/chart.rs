@@ -0,0 +1,47 @@
+use super::filter::TitleFilter;
+use crate::id::Id;
+use async_graphql::Enum;
+use sqlx::{QueryBuilder, Row, SqlitePool};
+
+#[derive(Enum, Copy, Clone, Eq, PartialEq, Default)]
+pub enum ChartOrder {
+    /// Bayesian weighted rating, the same way IMDb ranks its top 250
+    #[default]
+    TopRated,
+    MostVoted,
+}
+
+/// Get chart entries from the precomputed `chart_scores` table, returning title IDs with their weighted rating.
+pub async fn get_chart(
+    pool: &SqlitePool,
+    filter: &TitleFilter,
+    order: ChartOrder,
+    limit: i64,
+) -> Result<Vec<(Id, f32)>, sqlx::Error> {
+    let mut qb = QueryBuilder::new(
+        "SELECT c.id, c.weighted_rating
+        FROM chart_scores c
+        JOIN titles t ON t.id = c.id
+        JOIN ratings r ON r.id = c.id
+        WHERE 1 = 1",
+    );
+
+    // filter on the copied type so the chart indexes can drive the order
+    filter.push_kind_condition(&mut qb, "c.type");
+    filter.push_title_conditions(&mut qb);
+    match order {
+        ChartOrder::TopRated => qb.push(" ORDER BY c.weighted_rating DESC"),
+        ChartOrder::MostVoted => qb.push(" ORDER BY c.num_votes DESC"),
+    };
+
+    qb.push(" LIMIT ").push_bind(limit);
+
+    let rows = qb.build().fetch_all(pool).await?;
+    rows.into_iter()
+        .map(|row| {
+            let id: i64 = row.try_get("id")?;
+            let weighted_rating: f64 = row.try_get("weighted_rating")?;
+            Ok((id.into(), weighted_rating as f32))
+        })
+        .collect()
+}
@@ -1,22 +1,54 @@
 use super::title::{Title, TitleLoader};
-use crate::id::Id;
+use crate::id::{Id, NodeId};
 use async_graphql::*;
 use dataloader::DataLoader;
+use sqlx::{query_as, SqlitePool};
 
 #[derive(SimpleObject)]
 #[graphql(complex)]
 pub struct Episode {
+    /// The ID of the episode's title, exposed as `titleId` so `id` can be unique across nodes
+    #[graphql(skip)]
     pub id: Id,
     pub parent_id: Id,
-    pub season_number: i32,
-    pub episode_number: i32,
+    /// Missing for some specials and unnumbered episodes
+    pub season_number: Option<i32>,
+    /// Missing for some specials and unnumbered episodes
+    pub episode_number: Option<i32>,
+    /// The position of this episode across all seasons in airing order, starting at 1.
+    /// Specials (season 0) and episodes without a season or episode number don't have one.
+    pub absolute_number: Option<i64>,
 }
 
 #[ComplexObject]
 impl Episode {
+    pub async fn id(&self, _ctx: &Context<'_>) -> Result<ID> {
+        Ok(NodeId::Episode(self.id).into())
+    }
+
+    async fn title_id(&self) -> Id {
+        self.id
+    }
+
     async fn title(&self, ctx: &Context<'_>) -> Result<Title> {
         let loader = ctx.data::<DataLoader<TitleLoader>>()?;
         let title = loader.load_one(self.id).await?;
-        Ok(title.ok_or_else(|| Error::new("Title for episode not found"))?)
+        title
+            .map(Title::from)
+            .ok_or_else(|| Error::new("Title for episode not found"))
     }
 }
+
+pub async fn get_episode(pool: &SqlitePool, id: Id) -> Result<Option<Episode>, sqlx::Error> {
+    let id = id.get();
+    query_as!(
+        Episode,
+        "SELECT 
+        id, parent_id, season_number as \"season_number: _\", episode_number as \"episode_number: _\", absolute_number
+        FROM episodes
+        WHERE id = ?",
+        id
+    )
+    .fetch_optional(pool)
+    .await
+}
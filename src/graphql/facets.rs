@@ -0,0 +1,140 @@
+use super::{filter::TitleFilter, search::push_match_conditions};
+use crate::{genre::Genre, kind::TitleKind};
+use async_graphql::SimpleObject;
+use sqlx::{QueryBuilder, SqlitePool};
+
+#[derive(SimpleObject)]
+pub struct Facets {
+    pub total: i64,
+    pub kinds: Vec<KindFacet>,
+    pub genres: Vec<GenreFacet>,
+    pub decades: Vec<DecadeFacet>,
+    pub rating_bands: Vec<RatingBandFacet>,
+}
+
+#[derive(SimpleObject)]
+pub struct KindFacet {
+    pub kind: TitleKind,
+    pub count: i64,
+}
+
+#[derive(SimpleObject)]
+pub struct GenreFacet {
+    pub genre: Genre,
+    pub count: i64,
+}
+
+#[derive(SimpleObject)]
+pub struct DecadeFacet {
+    /// The first year of the decade, e.g. 1990
+    pub decade: i32,
+    pub count: i64,
+}
+
+#[derive(SimpleObject)]
+pub struct RatingBandFacet {
+    /// Inclusive lower bound of the band
+    pub min: i32,
+    /// Exclusive upper bound of the band, except for the 10 band which is only 10.0
+    pub max: i32,
+    pub count: i64,
+}
+
+/// Count the titles matching a search by kind, genre, decade and rating band.
+///
+/// The matches are collected once into a temp table, which is dropped again (or rolled back on errors)
+/// before the connection goes back to the pool.
+pub async fn get_facets(
+    pool: &SqlitePool,
+    query: Option<&str>,
+    filter: &TitleFilter,
+) -> Result<Facets, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let mut qb = QueryBuilder::new(
+        "CREATE TEMP TABLE facet_matches AS
+        SELECT t.id, t.type, t.start_year, r.average_rating
+        FROM titles t
+        LEFT JOIN ratings r ON r.id = t.id
+        WHERE 1 = 1",
+    );
+    push_match_conditions(&mut qb, query, filter);
+    qb.build().execute(&mut *tx).await?;
+
+    // the temp table doesn't exist at compile time, so these can't use the checked macros
+    let (total,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM facet_matches")
+        .fetch_one(&mut *tx)
+        .await?;
+
+    let kinds: Vec<(i64, i64)> = sqlx::query_as(
+        "SELECT type, COUNT(*) AS count FROM facet_matches GROUP BY type ORDER BY count DESC",
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+
+    let genres: Vec<(i64, i64)> = sqlx::query_as(
+        "SELECT tg.genre_id, COUNT(*) AS count
+        FROM facet_matches m
+        JOIN title_genres tg ON tg.title_id = m.id
+        GROUP BY tg.genre_id
+        ORDER BY count DESC, tg.genre_id",
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+
+    let decades: Vec<(i32, i64)> = sqlx::query_as(
+        "SELECT (start_year / 10) * 10 AS decade, COUNT(*)
+        FROM facet_matches
+        WHERE start_year IS NOT NULL
+        GROUP BY decade
+        ORDER BY decade",
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+
+    let rating_bands: Vec<(i32, i64)> = sqlx::query_as(
+        "SELECT CAST(average_rating AS INTEGER) AS band, COUNT(*)
+        FROM facet_matches
+        WHERE average_rating IS NOT NULL
+        GROUP BY band
+        ORDER BY band",
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+
+    sqlx::query("DROP TABLE facet_matches")
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    Ok(Facets {
+        total,
+        kinds: kinds
+            .into_iter()
+            .map(|(kind, count)| KindFacet {
+                kind: kind.into(),
+                count,
+            })
+            .collect(),
+        genres: genres
+            .into_iter()
+            .filter_map(|(genre, count)| {
+                Some(GenreFacet {
+                    genre: genre.try_into().ok()?,
+                    count,
+                })
+            })
+            .collect(),
+        decades: decades
+            .into_iter()
+            .map(|(decade, count)| DecadeFacet { decade, count })
+            .collect(),
+        rating_bands: rating_bands
+            .into_iter()
+            .map(|(min, count)| RatingBandFacet {
+                min,
+                max: (min + 1).min(10),
+                count,
+            })
+            .collect(),
+    })
+}
@@ -0,0 +1,70 @@
+use crate::{genre::Genre, kind::TitleKind};
+use async_graphql::InputObject;
+use sqlx::{QueryBuilder, Sqlite};
+
+#[derive(InputObject, Clone, Default)]
+pub struct TitleFilter {
+    pub kinds: Option<Vec<TitleKind>>,
+    /// Titles must have every genre in this list
+    pub genres: Option<Vec<Genre>>,
+    pub min_year: Option<i32>,
+    pub max_year: Option<i32>,
+    pub min_votes: Option<i64>,
+    pub min_rating: Option<f64>,
+    pub is_adult: Option<bool>,
+}
+
+impl TitleFilter {
+    /// Push `AND ...` conditions for this filter onto the query.
+    /// Expects `titles` to be aliased as `t` and `ratings` as `r`.
+    pub fn push_conditions(&self, qb: &mut QueryBuilder<'_, Sqlite>) {
+        self.push_kind_condition(qb, "t.type");
+        self.push_title_conditions(qb);
+    }
+
+    /// Push the `kinds` condition onto the query, checking the given type column.
+    /// Lets tables that copy the title type filter on their own indexed column.
+    pub fn push_kind_condition(&self, qb: &mut QueryBuilder<'_, Sqlite>, column: &str) {
+        if let Some(kinds) = &self.kinds {
+            if kinds.is_empty() {
+                qb.push(" AND 0");
+            } else {
+                qb.push(" AND ").push(column).push(" IN (");
+                let mut separated = qb.separated(", ");
+                for kind in kinds {
+                    separated.push_bind(*kind as i64);
+                }
+                qb.push(")");
+            }
+        }
+    }
+
+    /// Push every condition except `kinds` onto the query, with the same aliases as `push_conditions`.
+    pub fn push_title_conditions(&self, qb: &mut QueryBuilder<'_, Sqlite>) {
+        for genre in self.genres.iter().flatten() {
+            qb.push(" AND EXISTS (SELECT 1 FROM title_genres tg WHERE tg.title_id = t.id AND tg.genre_id = ")
+                .push_bind(*genre as i64)
+                .push(")");
+        }
+
+        if let Some(min_year) = self.min_year {
+            qb.push(" AND t.start_year >= ").push_bind(min_year);
+        }
+
+        if let Some(max_year) = self.max_year {
+            qb.push(" AND t.start_year <= ").push_bind(max_year);
+        }
+
+        if let Some(min_votes) = self.min_votes {
+            qb.push(" AND r.num_votes >= ").push_bind(min_votes);
+        }
+
+        if let Some(min_rating) = self.min_rating {
+            qb.push(" AND r.average_rating >= ").push_bind(min_rating);
+        }
+
+        if let Some(is_adult) = self.is_adult {
+            qb.push(" AND t.is_adult = ").push_bind(is_adult);
+        }
+    }
+}
@@ -0,0 +1,81 @@
+use super::facets::KindFacet;
+use crate::genre::Genre;
+use async_graphql::SimpleObject;
+use itertools::Itertools;
+use sqlx::SqlitePool;
+
+#[derive(SimpleObject)]
+pub struct GenreStats {
+    pub genre: Genre,
+    /// Number of titles with this genre
+    pub count: i64,
+    /// Number of titles with this genre per kind, most common first
+    pub kinds: Vec<KindFacet>,
+}
+
+/// Title counts for every genre, most common first.
+/// The per-kind breakdown needs a join against `titles`, so it is only computed if `with_kinds` is set.
+pub async fn get_genres(
+    pool: &SqlitePool,
+    with_kinds: bool,
+) -> Result<Vec<GenreStats>, sqlx::Error> {
+    if !with_kinds {
+        let rows: Vec<(i64, i64)> = sqlx::query_as(
+            "SELECT genre_id, COUNT(*) AS count
+            FROM title_genres
+            GROUP BY genre_id
+            ORDER BY count DESC, genre_id",
+        )
+        .fetch_all(pool)
+        .await?;
+
+        // ids this version doesn't know (written by a newer one) are skipped
+        return Ok(rows
+            .into_iter()
+            .filter_map(|(genre, count)| {
+                Some(GenreStats {
+                    genre: genre.try_into().ok()?,
+                    count,
+                    kinds: Vec::new(),
+                })
+            })
+            .collect());
+    }
+
+    let rows: Vec<(i64, i64, i64)> = sqlx::query_as(
+        "SELECT tg.genre_id, t.type, COUNT(*) AS count
+        FROM title_genres tg
+        JOIN titles t ON t.id = tg.title_id
+        GROUP BY tg.genre_id, t.type
+        ORDER BY tg.genre_id, count DESC",
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let genres = rows
+        .into_iter()
+        .chunk_by(|(genre, _, _)| *genre)
+        .into_iter()
+        .filter_map(|(genre, rows)| {
+            let genre = genre.try_into().ok()?;
+            let kinds = rows
+                .map(|(_, kind, count)| KindFacet {
+                    kind: kind.into(),
+                    count,
+                })
+                .collect_vec();
+            Some(GenreStats {
+                genre,
+                count: kinds.iter().map(|kind| kind.count).sum(),
+                kinds,
+            })
+        })
+        .sorted_by(|a, b| {
+            b.count
+                .cmp(&a.count)
+                .then((a.genre as i64).cmp(&(b.genre as i64)))
+        })
+        .collect();
+
+    Ok(genres)
+}
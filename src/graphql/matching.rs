@@ -0,0 +1,224 @@
+use super::{
+    filter::TitleFilter,
+    search::search,
+    title::{TitleData, TitleLoader, TitleWithRank},
+};
+use crate::{id::Id, kind::TitleKind, normalize::normalize_title};
+use async_graphql::{dataloader::DataLoader, InputObject, Result, SimpleObject};
+use futures::{stream, StreamExt, TryStreamExt};
+use itertools::Itertools;
+use sqlx::{QueryBuilder, Row, SqlitePool};
+use std::collections::{HashMap, HashSet};
+
+/// How many search results are scored for each name
+static CANDIDATE_POOL_SIZE: i64 = 20;
+
+/// Most inputs a single `matchTitles` call accepts
+pub const MAX_MATCH_INPUTS: usize = 100;
+
+/// How many inputs are looked up at the same time
+const MATCH_CONCURRENCY: usize = 8;
+
+#[derive(InputObject)]
+pub struct TitleMatchInput {
+    pub title: String,
+    pub year: Option<i32>,
+    pub kind: Option<TitleKind>,
+}
+
+#[derive(SimpleObject)]
+pub struct TitleMatch {
+    /// The best candidate, `rank` is the confidence from 0 to 1
+    pub best: Option<TitleWithRank>,
+    /// The next best candidates, best first
+    pub runners_up: Vec<TitleWithRank>,
+}
+
+pub struct Candidate {
+    pub title: TitleData,
+    /// 0 to 1, how well the title's names and year fit the input
+    pub confidence: f32,
+}
+
+/// Build an FTS5 query that matches every word in the name, without any FTS5 syntax leaking through.
+fn to_fts_query(name: &str) -> String {
+    normalize_title(name)
+        .split(' ')
+        .filter(|word| !word.is_empty())
+        .map(|word| format!("\"{word}\""))
+        .join(" ")
+}
+
+fn name_score(name: &str, candidate: &str) -> f32 {
+    let candidate = normalize_title(candidate);
+    if name == candidate {
+        return 1.0;
+    }
+
+    let a: HashSet<&str> = name.split(' ').collect();
+    let b: HashSet<&str> = candidate.split(' ').collect();
+    let shared = a.intersection(&b).count() as f32;
+    let total = a.union(&b).count() as f32;
+    0.8 * shared / total
+}
+
+fn year_score(year: Option<i32>, title: &TitleData) -> f32 {
+    let (Some(year), Some(start_year)) = (year, title.start_year) else {
+        return 0.5;
+    };
+
+    let end_year = title.end_year.unwrap_or(start_year);
+    if (start_year..=end_year).contains(&year) {
+        1.0
+    } else if (start_year - 1..=end_year + 1).contains(&year) {
+        0.7
+    } else {
+        0.0
+    }
+}
+
+/// Kinds that are often confused for each other, like a TV movie listed as a movie
+pub fn compatible_kinds(kind: TitleKind) -> Vec<TitleKind> {
+    use TitleKind::*;
+    let groups: [&[TitleKind]; 4] = [
+        &[Movie, TvMovie, Video],
+        &[TvSeries, TvMiniSeries],
+        &[Short, TvShort],
+        &[TvEpisode, TvPilot],
+    ];
+
+    groups
+        .into_iter()
+        .find(|group| group.contains(&kind))
+        .map(|group| group.to_vec())
+        .unwrap_or_else(|| vec![kind])
+}
+
+fn kind_score(kind: Option<TitleKind>, title: &TitleData) -> f32 {
+    match kind {
+        Some(kind) if kind != title.kind => 0.85,
+        _ => 1.0,
+    }
+}
+
+/// Find titles matching a name and optional year through the search index, best first.
+/// Candidates are limited to `kinds` if given, and candidates that aren't exactly `preferred_kind` score lower.
+pub async fn find_candidates(
+    pool: &SqlitePool,
+    loader: &DataLoader<TitleLoader>,
+    name: &str,
+    year: Option<i32>,
+    kinds: Option<Vec<TitleKind>>,
+    preferred_kind: Option<TitleKind>,
+    limit: usize,
+) -> Result<Vec<Candidate>> {
+    let query = to_fts_query(name);
+    if query.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let filter = TitleFilter {
+        kinds,
+        ..Default::default()
+    };
+
+    let results = search(pool, &query, &filter, CANDIDATE_POOL_SIZE).await?;
+    if results.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let ids = results.iter().map(|(id, _)| *id).collect_vec();
+    let mut titles = loader.load_many(ids.iter().copied()).await?;
+    let akas = get_aka_names(pool, &ids).await?;
+
+    let name = normalize_title(name);
+    let count = results.len() as f32;
+    let mut candidates = results
+        .into_iter()
+        .enumerate()
+        .filter_map(|(position, (id, _))| {
+            let title = titles.remove(&id)?;
+            let name_score = [Some(&title.primary_title), title.original_title.as_ref()]
+                .into_iter()
+                .flatten()
+                .chain(akas.get(&id).into_iter().flatten())
+                .map(|candidate| name_score(&name, candidate))
+                .fold(0.0, f32::max);
+
+            // search order already accounts for popularity, use it to break ties
+            let search_score = 1.0 - position as f32 / count;
+            let confidence =
+                (0.6 * name_score + 0.3 * year_score(year, &title) + 0.1 * search_score)
+                    * kind_score(preferred_kind, &title);
+            Some(Candidate { title, confidence })
+        })
+        .collect_vec();
+
+    candidates.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+    candidates.truncate(limit);
+    Ok(candidates)
+}
+
+async fn get_aka_names(
+    pool: &SqlitePool,
+    ids: &[Id],
+) -> Result<HashMap<Id, Vec<String>>, sqlx::Error> {
+    let mut qb = QueryBuilder::new("SELECT id, title FROM akas WHERE id IN (");
+    let mut separated = qb.separated(", ");
+    for id in ids {
+        separated.push_bind(id.get());
+    }
+    qb.push(")");
+
+    let rows = qb.build().fetch_all(pool).await?;
+    let mut names: HashMap<Id, Vec<String>> = HashMap::new();
+    for row in rows {
+        let id: i64 = row.try_get("id")?;
+        names
+            .entry(id.into())
+            .or_default()
+            .push(row.try_get("title")?);
+    }
+
+    Ok(names)
+}
+
+/// Match each input to its best candidates, returning results in the same order as the inputs.
+pub async fn match_titles(
+    pool: &SqlitePool,
+    loader: &DataLoader<TitleLoader>,
+    inputs: Vec<TitleMatchInput>,
+    runners_up: usize,
+) -> Result<Vec<TitleMatch>> {
+    if inputs.len() > MAX_MATCH_INPUTS {
+        return Err(format!("at most {MAX_MATCH_INPUTS} titles can be matched at once").into());
+    }
+
+    stream::iter(inputs)
+        .map(|input| async move {
+            let kinds = input.kind.map(compatible_kinds);
+            let candidates = find_candidates(
+                pool,
+                loader,
+                &input.title,
+                input.year,
+                kinds,
+                input.kind,
+                runners_up + 1,
+            )
+            .await?;
+
+            let mut candidates = candidates.into_iter().map(|candidate| TitleWithRank {
+                rank: Some(candidate.confidence),
+                data: candidate.title,
+            });
+
+            Ok(TitleMatch {
+                best: candidates.next(),
+                runners_up: candidates.collect(),
+            })
+        })
+        .buffered(MATCH_CONCURRENCY)
+        .try_collect()
+        .await
+}
@@ -1,106 +1,247 @@
 use std::collections::HashMap;
 
-use crate::id::Id;
-use async_graphql::{dataloader::DataLoader, Context, Object, Result};
+use crate::genre::Genre;
+use crate::id::{Id, NodeId};
+use crate::kind::TitleKind;
+use async_graphql::{dataloader::DataLoader, Context, Object, Result, ID};
+use chart::{get_chart, ChartOrder};
+use facets::{get_facets, Facets};
+use filter::TitleFilter;
+use futures::future::try_join_all;
+use genres::{get_genres, GenreStats};
+use matching::{match_titles, TitleMatch, TitleMatchInput};
+use node::{get_node, Node};
+use random::get_random;
+use release::{resolve_release, ReleaseMatch};
 use sqlx::SqlitePool;
-use title::{Title, TitleLoader, TitleWithRank};
+use stats::Stats;
+use title::{Locale, Title, TitleLoader, TitleWithRank};
 
+mod aka;
+mod chart;
 mod episode;
+mod facets;
+mod filter;
+mod genres;
+mod matching;
+mod node;
+mod random;
+mod release;
+mod search;
+mod series;
+mod similar;
+mod stats;
 pub mod title;
 
 pub struct Query;
 
 #[Object]
 impl Query {
-    async fn title(&self, ctx: &Context<'_>, id: Id) -> Result<Option<Title>> {
-        let loader = ctx.data::<DataLoader<TitleLoader>>()?;
-        let title = loader.load_one(id).await?;
-        Ok(title)
-    }
-
     async fn titles(
         &self,
         ctx: &Context<'_>,
         query: Option<String>,
         ids: Option<Vec<Id>>,
         limit: Option<usize>,
+        filter: Option<TitleFilter>,
+        locale: Option<Locale>,
     ) -> Result<Vec<TitleWithRank>> {
+        let has_filter = filter.is_some();
         let mut ids = ids.unwrap_or_default();
         let mut scores = HashMap::new();
         let pool = ctx.data::<SqlitePool>()?;
 
+        let filter = filter.unwrap_or_default();
+        let limit = limit.unwrap_or(25) as i64;
         if let Some(query) = query {
-            let limit = limit.unwrap_or(25) as i64;
-            let escaped_query = query.replace(":", "");
-            let search_results = sqlx::query!(
-                r#"
-                SELECT 
-                    si.title_id AS "title_id: i64",
-                    -bm25(search_index)
-                    +
-                    (
-                        CASE
-                            WHEN r.num_votes < 10 THEN 1.0
-                            WHEN r.num_votes < 100 THEN 1.5
-                            WHEN r.num_votes < 1000 THEN 2.0
-                            WHEN r.num_votes < 10000 THEN 2.5
-                            ELSE 2.5
-                        END
-                    )
-                    +
-                    (
-                        CASE 
-                            WHEN si.is_display = 1 THEN 1.0 
-                            ELSE -5.0 
-                        END
-                    ) AS final_score
-                FROM search_index si
-                LEFT JOIN ratings r ON r.id = si.title_id
-                LEFT JOIN titles t ON t.id = si.title_id
-                WHERE 
-                    text MATCH ?
-                    AND r.id IS NOT NULL 
-                    AND t.type IN (0, 1, 3, 4, 6, 10)
-                ORDER BY final_score DESC
-                LIMIT ?
-                "#,
-                escaped_query,
-                limit
-            )
-            .fetch_all(pool)
-            .await?;
-
-            for result in search_results {
-                let title_id: Id = result.title_id.expect("missing title_id").into();
+            for (title_id, score) in search::search(pool, &query, &filter, limit).await? {
                 ids.push(title_id);
-                scores.insert(title_id, result.final_score as f32);
+                scores.insert(title_id, score);
             }
-        } else {
-            if ids.is_empty() {
-                return Err("'query' or 'ids' is required'".into());
+        } else if ids.is_empty() {
+            if !has_filter {
+                return Err("'query', 'ids' or 'filter' is required".into());
             }
+
+            ids = search::browse(pool, &filter, limit).await?;
         }
 
+        let positions: HashMap<Id, usize> =
+            ids.iter().enumerate().map(|(i, id)| (*id, i)).collect();
         let loader = ctx.data::<DataLoader<TitleLoader>>()?;
         let titles = loader.load_many(ids).await?;
 
         let mut titles = titles
-            .into_iter()
-            .map(|(_, title)| {
-                let id = title.id;
-                TitleWithRank {
-                    rank: scores.get(&id).copied(),
-                    title,
-                }
+            .into_values()
+            .map(|mut data| {
+                data.locale = locale.clone();
+                data
             })
             .collect::<Vec<_>>();
 
+        // keep the order of `ids` (or browse results) for titles that have no rank
+        titles.sort_by_key(|data| positions.get(&data.id).copied());
         titles.sort_by(|a, b| {
-            let a_score = a.rank.unwrap_or(0.0);
-            let b_score = b.rank.unwrap_or(0.0);
+            let a_score = scores.get(&a.id).copied().unwrap_or(0.0);
+            let b_score = scores.get(&b.id).copied().unwrap_or(0.0);
             b_score.partial_cmp(&a_score).unwrap()
         });
 
-        Ok(titles)
+        Ok(titles
+            .into_iter()
+            .map(|data| TitleWithRank {
+                rank: scores.get(&data.id).copied(),
+                data,
+            })
+            .collect())
+    }
+
+    async fn title(&self, ctx: &Context<'_>, id: Id) -> Result<Option<Title>> {
+        let loader = ctx.data::<DataLoader<TitleLoader>>()?;
+        let title = loader.load_one(id).await?;
+        Ok(title.map(Title::from))
+    }
+
+    /// Fetches any object by its global `id`
+    async fn node(&self, ctx: &Context<'_>, id: ID) -> Result<Option<Node>> {
+        let id = id.parse::<NodeId>()?;
+        let pool = ctx.data::<SqlitePool>()?;
+        let loader = ctx.data::<DataLoader<TitleLoader>>()?;
+        get_node(pool, loader, id).await
+    }
+
+    /// Nodes in the same order as `ids`, null for IDs that don't exist
+    async fn nodes(&self, ctx: &Context<'_>, ids: Vec<ID>) -> Result<Vec<Option<Node>>> {
+        let ids = ids
+            .iter()
+            .map(|id| id.parse::<NodeId>())
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        let pool = ctx.data::<SqlitePool>()?;
+        let loader = ctx.data::<DataLoader<TitleLoader>>()?;
+        try_join_all(ids.into_iter().map(|id| get_node(pool, loader, id))).await
+    }
+
+    /// Titles whose primary title, original title or an aka equals `name`, ignoring case, accents and punctuation.
+    /// Unlike `titles(query:)` this doesn't match partial names, most voted titles come first.
+    async fn titles_by_exact_name(
+        &self,
+        ctx: &Context<'_>,
+        name: String,
+        year: Option<i32>,
+        kinds: Option<Vec<TitleKind>>,
+        limit: Option<usize>,
+    ) -> Result<Vec<Title>> {
+        let pool = ctx.data::<SqlitePool>()?;
+        let filter = TitleFilter {
+            kinds,
+            min_year: year,
+            max_year: year,
+            ..Default::default()
+        };
+
+        let limit = limit.unwrap_or(25) as i64;
+        let ids = search::exact(pool, &name, &filter, limit).await?;
+        let loader = ctx.data::<DataLoader<TitleLoader>>()?;
+        let mut titles = loader.load_many(ids.iter().copied()).await?;
+        Ok(ids
+            .iter()
+            .filter_map(|id| titles.remove(id))
+            .map(Title::from)
+            .collect())
+    }
+
+    /// Uniformly random titles matching the filter, at most 100.
+    /// Passing the same `seed` returns the same titles until the next dataset import.
+    async fn random_titles(
+        &self,
+        ctx: &Context<'_>,
+        filter: Option<TitleFilter>,
+        count: Option<usize>,
+        seed: Option<i64>,
+    ) -> Result<Vec<Title>> {
+        let pool = ctx.data::<SqlitePool>()?;
+        let filter = filter.unwrap_or_default();
+        let count = count.unwrap_or(10).min(100);
+        let ids = get_random(pool, &filter, count, seed).await?;
+        let loader = ctx.data::<DataLoader<TitleLoader>>()?;
+        let mut titles = loader.load_many(ids.iter().copied()).await?;
+        Ok(ids
+            .iter()
+            .filter_map(|id| titles.remove(id))
+            .map(Title::from)
+            .collect())
+    }
+
+    /// Titles ranked by weighted rating or vote count, like IMDb's top 250.
+    /// `rank` is the weighted rating of each title.
+    #[allow(clippy::too_many_arguments)]
+    async fn chart(
+        &self,
+        ctx: &Context<'_>,
+        kind: Option<TitleKind>,
+        genre: Option<Genre>,
+        #[graphql(desc = "The first year of the decade, e.g. 1990")] decade: Option<i32>,
+        min_votes: Option<i64>,
+        #[graphql(default)] order: ChartOrder,
+        limit: Option<usize>,
+    ) -> Result<Vec<TitleWithRank>> {
+        let pool = ctx.data::<SqlitePool>()?;
+        let filter = TitleFilter {
+            kinds: kind.map(|kind| vec![kind]),
+            genres: genre.map(|genre| vec![genre]),
+            min_year: decade,
+            max_year: decade.map(|decade| decade + 9),
+            min_votes,
+            ..Default::default()
+        };
+
+        let limit = limit.unwrap_or(250) as i64;
+        let entries = get_chart(pool, &filter, order, limit).await?;
+        let loader = ctx.data::<DataLoader<TitleLoader>>()?;
+        TitleWithRank::load_ranked(loader, entries, None).await
+    }
+
+    /// Row counts, histograms and import times for the whole dataset
+    async fn stats(&self) -> Stats {
+        Stats
+    }
+
+    /// Every genre with the number of titles that have it, most common first
+    async fn genres(&self, ctx: &Context<'_>) -> Result<Vec<GenreStats>> {
+        let pool = ctx.data::<SqlitePool>()?;
+        let with_kinds = ctx.look_ahead().field("kinds").exists();
+        Ok(get_genres(pool, with_kinds).await?)
+    }
+
+    /// Match a media file or release name like "The.Expanse.S02E05.1080p.WEB.mkv" to a title or episode.
+    async fn resolve_release(&self, ctx: &Context<'_>, name: String) -> Result<ReleaseMatch> {
+        let pool = ctx.data::<SqlitePool>()?;
+        let loader = ctx.data::<DataLoader<TitleLoader>>()?;
+        resolve_release(pool, loader, &name).await
+    }
+
+    /// Match many (title, year, kind) rows at once, like a spreadsheet of titles.
+    /// Results are in the same order as `inputs`, which holds at most 100 rows.
+    async fn match_titles(
+        &self,
+        ctx: &Context<'_>,
+        inputs: Vec<TitleMatchInput>,
+        #[graphql(default = 3)] runners_up: usize,
+    ) -> Result<Vec<TitleMatch>> {
+        let pool = ctx.data::<SqlitePool>()?;
+        let loader = ctx.data::<DataLoader<TitleLoader>>()?;
+        match_titles(pool, loader, inputs, runners_up).await
+    }
+
+    /// Counts over every title matching the query and filter, ignoring `limit`.
+    async fn facets(
+        &self,
+        ctx: &Context<'_>,
+        query: Option<String>,
+        filter: Option<TitleFilter>,
+    ) -> Result<Facets> {
+        let pool = ctx.data::<SqlitePool>()?;
+        let filter = filter.unwrap_or_default();
+        Ok(get_facets(pool, query.as_deref(), &filter).await?)
     }
 }
@@ -0,0 +1,50 @@
+use super::{
+    aka::{get_aka, Aka},
+    episode::{get_episode, Episode},
+    title::{EpisodeTitle, Movie, OtherTitle, Series, Short, Title, TitleLoader, VideoGame},
+};
+use crate::id::NodeId;
+use async_graphql::{dataloader::DataLoader, Interface, Result, ID};
+use sqlx::SqlitePool;
+
+/// Anything with a globally unique `id`, for Relay-style object identification and normalized caches.
+#[derive(Interface)]
+#[graphql(field(name = "id", ty = "ID"))]
+pub enum Node {
+    Movie(Movie),
+    Series(Series),
+    EpisodeTitle(EpisodeTitle),
+    Short(Short),
+    VideoGame(VideoGame),
+    OtherTitle(OtherTitle),
+    Episode(Episode),
+    Aka(Aka),
+}
+
+impl From<Title> for Node {
+    fn from(title: Title) -> Self {
+        match title {
+            Title::Movie(title) => Node::Movie(title),
+            Title::Series(title) => Node::Series(title),
+            Title::Episode(title) => Node::EpisodeTitle(title),
+            Title::Short(title) => Node::Short(title),
+            Title::VideoGame(title) => Node::VideoGame(title),
+            Title::Other(title) => Node::OtherTitle(title),
+        }
+    }
+}
+
+pub async fn get_node(
+    pool: &SqlitePool,
+    loader: &DataLoader<TitleLoader>,
+    id: NodeId,
+) -> Result<Option<Node>> {
+    Ok(match id {
+        NodeId::Title(id) => loader
+            .load_one(id)
+            .await?
+            .map(|data| Title::from(data).into()),
+        NodeId::Episode(id) => get_episode(pool, id).await?.map(Node::Episode),
+        NodeId::Aka(id, ordering) => get_aka(pool, id, ordering).await?.map(Node::Aka),
+    })
+}
@@ -0,0 +1,144 @@
+use super::filter::TitleFilter;
+use crate::id::Id;
+use rand::{seq::SliceRandom, Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use sqlx::{QueryBuilder, Row, SqlitePool};
+use std::collections::HashSet;
+
+/// Random IDs checked per query
+const SAMPLE_BATCH_SIZE: usize = 1000;
+/// Batches to try before walking the ID index for the remaining titles
+const MAX_SAMPLE_BATCHES: usize = 20;
+
+/// Pick up to `count` distinct titles matching the filter at random.
+///
+/// IMDb IDs are sparse, so this draws random IDs between the lowest and highest title ID and keeps the ones
+/// that exist and match, which makes every matching title equally likely. Each draw is a primary key lookup.
+///
+/// The first batch of draws also estimates how common matches are. If all `MAX_SAMPLE_BATCHES` are unlikely to
+/// find enough of them, the remaining titles are taken by walking the ID index from a random ID instead,
+/// wrapping around at the end. That reads rows until it found enough matches, which for very selective filters
+/// can be a large part of the table, but it never sorts or collects every match. Walked titles are less random:
+/// they come from one stretch of IDs, and matches after a long gap are more likely to be picked.
+///
+/// The same seed returns the same titles as long as the dataset doesn't change.
+pub async fn get_random(
+    pool: &SqlitePool,
+    filter: &TitleFilter,
+    count: usize,
+    seed: Option<i64>,
+) -> Result<Vec<Id>, sqlx::Error> {
+    let mut rng = match seed {
+        Some(seed) => ChaCha8Rng::seed_from_u64(seed as u64),
+        None => ChaCha8Rng::from_entropy(),
+    };
+
+    let (min_id, max_id): (Option<i64>, Option<i64>) =
+        sqlx::query_as("SELECT MIN(id), MAX(id) FROM titles")
+            .fetch_one(pool)
+            .await?;
+    let (Some(min_id), Some(max_id)) = (min_id, max_id) else {
+        return Ok(Vec::new());
+    };
+
+    let mut picked = Vec::new();
+    let mut seen = HashSet::new();
+    for batch in 0..MAX_SAMPLE_BATCHES {
+        if picked.len() >= count {
+            break;
+        }
+
+        let found = sample_batch(pool, filter, min_id, max_id, &mut rng).await?;
+        // sample only if all batches are expected to find at least twice as many titles as needed
+        let is_sparse = batch == 0 && found.len() * MAX_SAMPLE_BATCHES < 2 * count;
+        for id in found {
+            if picked.len() < count && seen.insert(id) {
+                picked.push(id);
+            }
+        }
+
+        if is_sparse {
+            break;
+        }
+    }
+
+    if picked.len() < count {
+        let start = rng.gen_range(min_id..=max_id);
+        let mut walked = walk_from(pool, filter, start, count)
+            .await?
+            .into_iter()
+            .filter(|id| !seen.contains(id))
+            .collect::<Vec<_>>();
+        walked.shuffle(&mut rng);
+        walked.truncate(count - picked.len());
+        picked.extend(walked);
+    }
+
+    Ok(picked.into_iter().map(Id::from).collect())
+}
+
+/// Draw `SAMPLE_BATCH_SIZE` random IDs and return the ones that exist and match, in draw order.
+async fn sample_batch(
+    pool: &SqlitePool,
+    filter: &TitleFilter,
+    min_id: i64,
+    max_id: i64,
+    rng: &mut ChaCha8Rng,
+) -> Result<Vec<i64>, sqlx::Error> {
+    let candidates = (0..SAMPLE_BATCH_SIZE)
+        .map(|_| rng.gen_range(min_id..=max_id))
+        .collect::<Vec<_>>();
+
+    let mut qb = QueryBuilder::new(
+        "SELECT t.id FROM titles t LEFT JOIN ratings r ON r.id = t.id WHERE t.id IN (",
+    );
+    let mut separated = qb.separated(", ");
+    for id in &candidates {
+        separated.push_bind(*id);
+    }
+    qb.push(")");
+    filter.push_conditions(&mut qb);
+
+    let matches = qb
+        .build()
+        .fetch_all(pool)
+        .await?
+        .into_iter()
+        .map(|row| row.try_get::<i64, _>("id"))
+        .collect::<Result<HashSet<_>, _>>()?;
+
+    // keep draw order so results don't depend on the order SQLite returns rows in
+    Ok(candidates
+        .into_iter()
+        .filter(|id| matches.contains(id))
+        .collect())
+}
+
+/// The first `limit` matches in ID order starting at `start`, continuing from the lowest ID at the end.
+async fn walk_from(
+    pool: &SqlitePool,
+    filter: &TitleFilter,
+    start: i64,
+    limit: usize,
+) -> Result<Vec<i64>, sqlx::Error> {
+    let mut ids = Vec::new();
+    for (condition, bound) in [(" AND t.id >= ", start), (" AND t.id < ", start)] {
+        if ids.len() >= limit {
+            break;
+        }
+
+        let mut qb = QueryBuilder::new(
+            "SELECT t.id FROM titles t LEFT JOIN ratings r ON r.id = t.id WHERE 1 = 1",
+        );
+        qb.push(condition).push_bind(bound);
+        filter.push_conditions(&mut qb);
+        qb.push(" ORDER BY t.id LIMIT ")
+            .push_bind((limit - ids.len()) as i64);
+
+        for row in qb.build().fetch_all(pool).await? {
+            ids.push(row.try_get::<i64, _>("id")?);
+        }
+    }
+
+    Ok(ids)
+}
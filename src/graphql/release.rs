@@ -0,0 +1,91 @@
+use super::{
+    episode::Episode,
+    matching::find_candidates,
+    title::{Title, TitleData, TitleLoader},
+};
+use crate::{
+    kind::TitleKind,
+    release::{parse_release, ParsedRelease},
+};
+use async_graphql::{dataloader::DataLoader, Result, SimpleObject};
+use sqlx::{query_as, SqlitePool};
+
+/// How many of the best series are checked for the episode before giving up
+static EPISODE_CANDIDATES: usize = 3;
+
+#[derive(SimpleObject)]
+pub struct ReleaseMatch {
+    pub parsed: ParsedRelease,
+    /// The movie or series, null if nothing matched
+    pub title: Option<Title>,
+    /// The episode if the release had a season and episode number that exists for the series
+    pub episode: Option<Episode>,
+    /// 0 to 1, how sure we are that `title` and `episode` are correct
+    pub confidence: f32,
+}
+
+pub async fn resolve_release(
+    pool: &SqlitePool,
+    loader: &DataLoader<TitleLoader>,
+    name: &str,
+) -> Result<ReleaseMatch> {
+    let parsed = parse_release(name);
+    let is_episode = parsed.season_number.is_some() && parsed.episode_number.is_some();
+    let kinds = is_episode.then(|| vec![TitleKind::TvSeries, TitleKind::TvMiniSeries]);
+    let candidates =
+        find_candidates(pool, loader, &parsed.title, parsed.year, kinds, None, 10).await?;
+
+    if !is_episode {
+        let best = candidates.into_iter().next();
+        return Ok(ReleaseMatch {
+            parsed,
+            confidence: best.as_ref().map_or(0.0, |c| c.confidence),
+            title: best.map(|c| c.title.into()),
+            episode: None,
+        });
+    }
+
+    // a series that doesn't have the episode is likely the wrong one,
+    // so check the next few candidates before falling back to the best series alone
+    let mut best: Option<(TitleData, Option<Episode>, f32)> = None;
+    for candidate in candidates.into_iter().take(EPISODE_CANDIDATES) {
+        let parent_id = candidate.title.id.get();
+        let episode = query_as!(
+            Episode,
+            "SELECT 
+            id, parent_id, season_number as \"season_number: _\", episode_number as \"episode_number: _\", absolute_number
+            FROM episodes
+            WHERE parent_id = ? AND season_number = ? AND episode_number = ?
+            LIMIT 1",
+            parent_id,
+            parsed.season_number,
+            parsed.episode_number
+        )
+        .fetch_optional(pool)
+        .await?;
+
+        let confidence = match episode {
+            Some(_) => candidate.confidence,
+            None => candidate.confidence * 0.5,
+        };
+
+        if best.as_ref().is_none_or(|(_, _, best)| confidence > *best) {
+            best = Some((candidate.title, episode, confidence));
+        }
+    }
+
+    Ok(match best {
+        Some((title, episode, confidence)) => ReleaseMatch {
+            parsed,
+            title: Some(title.into()),
+            episode,
+            confidence,
+        },
+        None => ReleaseMatch {
+            parsed,
+            title: None,
+            episode: None,
+            confidence: 0.0,
+        },
+    })
+}
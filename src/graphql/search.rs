@@ -0,0 +1,155 @@
+use super::filter::TitleFilter;
+use crate::{id::Id, kind::TitleKind, normalize::normalize_title};
+use sqlx::{QueryBuilder, Row, Sqlite, SqlitePool};
+
+/// Kinds returned by text search unless the filter asks for specific kinds.
+/// Episodes, pilots, videos etc. mostly add noise to results.
+pub const DEFAULT_SEARCH_KINDS: [TitleKind; 6] = [
+    TitleKind::Movie,
+    TitleKind::Short,
+    TitleKind::TvMiniSeries,
+    TitleKind::TvMovie,
+    TitleKind::TvSeries,
+    TitleKind::VideoGame,
+];
+
+pub fn escape_query(query: &str) -> String {
+    query.replace(":", "")
+}
+
+/// Push the conditions that decide whether a title matches a search.
+/// Expects `titles` to be aliased as `t` and `ratings` as `r`.
+pub fn push_match_conditions(
+    qb: &mut QueryBuilder<'_, Sqlite>,
+    query: Option<&str>,
+    filter: &TitleFilter,
+) {
+    if let Some(query) = query {
+        qb.push(" AND t.id IN (SELECT title_id FROM search_index WHERE text MATCH ")
+            .push_bind(escape_query(query))
+            .push(") AND r.id IS NOT NULL");
+        push_default_kinds(qb, filter);
+    }
+
+    filter.push_conditions(qb);
+}
+
+fn push_default_kinds(qb: &mut QueryBuilder<'_, Sqlite>, filter: &TitleFilter) {
+    if filter.kinds.is_none() {
+        qb.push(" AND t.type IN (");
+        let mut separated = qb.separated(", ");
+        for kind in DEFAULT_SEARCH_KINDS {
+            separated.push_bind(kind as i64);
+        }
+        qb.push(")");
+    }
+}
+
+/// Run a ranked full-text search, returning title IDs with their scores, best first.
+pub async fn search(
+    pool: &SqlitePool,
+    query: &str,
+    filter: &TitleFilter,
+    limit: i64,
+) -> Result<Vec<(Id, f32)>, sqlx::Error> {
+    let mut qb = QueryBuilder::new(
+        "SELECT 
+            si.title_id AS title_id,
+            -bm25(search_index)
+            +
+            (
+                CASE
+                    WHEN r.num_votes < 10 THEN 1.0
+                    WHEN r.num_votes < 100 THEN 1.5
+                    WHEN r.num_votes < 1000 THEN 2.0
+                    WHEN r.num_votes < 10000 THEN 2.5
+                    ELSE 2.5
+                END
+            )
+            +
+            (
+                CASE 
+                    WHEN si.is_display = 1 THEN 1.0 
+                    ELSE -5.0 
+                END
+            ) AS final_score
+        FROM search_index si
+        LEFT JOIN ratings r ON r.id = si.title_id
+        LEFT JOIN titles t ON t.id = si.title_id
+        WHERE 
+            text MATCH ",
+    );
+
+    qb.push_bind(escape_query(query));
+    qb.push(" AND r.id IS NOT NULL");
+    push_default_kinds(&mut qb, filter);
+    filter.push_conditions(&mut qb);
+    qb.push(" ORDER BY final_score DESC LIMIT ")
+        .push_bind(limit);
+
+    let rows = qb.build().fetch_all(pool).await?;
+    rows.into_iter()
+        .map(|row| {
+            let title_id: i64 = row.try_get("title_id")?;
+            let score: f64 = row.try_get("final_score")?;
+            Ok((title_id.into(), score as f32))
+        })
+        .collect()
+}
+
+/// List titles matching the filter without a text query, most voted first.
+pub async fn browse(
+    pool: &SqlitePool,
+    filter: &TitleFilter,
+    limit: i64,
+) -> Result<Vec<Id>, sqlx::Error> {
+    let mut qb = QueryBuilder::new(
+        "SELECT t.id FROM titles t LEFT JOIN ratings r ON r.id = t.id WHERE 1 = 1",
+    );
+
+    filter.push_conditions(&mut qb);
+    qb.push(" ORDER BY r.num_votes DESC NULLS LAST LIMIT ")
+        .push_bind(limit);
+
+    let rows = qb.build().fetch_all(pool).await?;
+    rows.into_iter()
+        .map(|row| Ok(row.try_get::<i64, _>("id")?.into()))
+        .collect()
+}
+
+/// List titles whose primary title, original title or any aka equals the name after normalization,
+/// most voted first. Uses the normalized title indexes instead of the search index.
+/// Names that normalize to nothing, like "!!!", match no titles.
+pub async fn exact(
+    pool: &SqlitePool,
+    name: &str,
+    filter: &TitleFilter,
+    limit: i64,
+) -> Result<Vec<Id>, sqlx::Error> {
+    let name = normalize_title(name);
+    // punctuation-only names would match every title whose normalized title is empty
+    if name.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut qb = QueryBuilder::new(
+        "SELECT t.id FROM titles t LEFT JOIN ratings r ON r.id = t.id WHERE t.id IN (
+            SELECT id FROM titles WHERE normalized_primary_title = ",
+    );
+
+    qb.push_bind(name.clone())
+        .push(" UNION SELECT id FROM titles WHERE normalized_original_title = ")
+        .push_bind(name.clone())
+        .push(" UNION SELECT id FROM akas WHERE normalized_title = ")
+        .push_bind(name)
+        .push(")");
+
+    filter.push_conditions(&mut qb);
+    qb.push(" ORDER BY r.num_votes DESC NULLS LAST LIMIT ")
+        .push_bind(limit);
+
+    let rows = qb.build().fetch_all(pool).await?;
+    rows.into_iter()
+        .map(|row| Ok(row.try_get::<i64, _>("id")?.into()))
+        .collect()
+}
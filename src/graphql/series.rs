@@ -0,0 +1,146 @@
+use super::episode::Episode;
+use crate::id::Id;
+use async_graphql::SimpleObject;
+use itertools::Itertools;
+use sqlx::{query, query_as, SqlitePool};
+
+#[derive(SimpleObject)]
+pub struct SeriesStats {
+    pub episode_count: i64,
+    /// Specials (season 0) and episodes without a season number aren't counted
+    pub season_count: i64,
+    /// Sum of the runtime of every episode that has one
+    pub total_runtime_minutes: Option<i64>,
+    pub rated_episode_count: i64,
+    /// Average rating of rated episodes, weighted by their number of votes
+    pub average_episode_rating: Option<f32>,
+    pub highest_rated_episode: Option<Episode>,
+    pub lowest_rated_episode: Option<Episode>,
+}
+
+#[derive(SimpleObject)]
+pub struct Season {
+    /// Null for episodes without a season number
+    pub season_number: Option<i32>,
+    pub episodes: Vec<Episode>,
+}
+
+/// Group episodes that are already in airing order into seasons.
+pub fn group_seasons(episodes: Vec<Episode>) -> Vec<Season> {
+    episodes
+        .into_iter()
+        .chunk_by(|episode| episode.season_number)
+        .into_iter()
+        .map(|(season_number, episodes)| Season {
+            season_number,
+            episodes: episodes.collect(),
+        })
+        .collect()
+}
+
+/// Seasons with more missing episode numbers than this don't list them
+const MAX_MISSING_EPISODE_NUMBERS: i32 = 1000;
+
+#[derive(SimpleObject)]
+pub struct SeasonGaps {
+    pub season_number: i32,
+    pub episode_count: i64,
+    pub max_episode_number: i32,
+    /// Episode numbers between 1 and `max_episode_number` that no episode has.
+    /// Null if more than 1000 are missing, like for seasons numbered by air date.
+    pub missing_episode_numbers: Option<Vec<i32>>,
+}
+
+pub async fn get_episode_gaps(pool: &SqlitePool, id: Id) -> Result<Vec<SeasonGaps>, sqlx::Error> {
+    let id = id.get();
+    let episodes = query!(
+        "SELECT season_number AS \"season_number!: i32\", episode_number AS \"episode_number!: i32\"
+        FROM episodes
+        WHERE parent_id = ? AND season_number IS NOT NULL AND episode_number IS NOT NULL
+        ORDER BY season_number, episode_number",
+        id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(episodes
+        .into_iter()
+        .chunk_by(|episode| episode.season_number)
+        .into_iter()
+        .map(|(season_number, episodes)| {
+            let numbers = episodes.map(|e| e.episode_number).collect_vec();
+            let episode_count = numbers.len() as i64;
+            let max_episode_number = numbers.last().copied().unwrap_or(0);
+            let unique = numbers.iter().dedup().filter(|n| **n >= 1).count() as i32;
+            let missing_episode_numbers =
+                (max_episode_number - unique <= MAX_MISSING_EPISODE_NUMBERS).then(|| {
+                    (1..max_episode_number)
+                        .filter(|n| numbers.binary_search(n).is_err())
+                        .collect()
+                });
+            SeasonGaps {
+                season_number,
+                episode_count,
+                max_episode_number,
+                missing_episode_numbers,
+            }
+        })
+        .collect())
+}
+
+pub async fn get_series_stats(pool: &SqlitePool, id: Id) -> Result<SeriesStats, sqlx::Error> {
+    let id = id.get();
+    let totals = query!(
+        "SELECT
+            COUNT(*) AS \"episode_count!: i64\",
+            COUNT(DISTINCT NULLIF(e.season_number, 0)) AS \"season_count!: i64\",
+            SUM(t.runtime_minutes) AS \"total_runtime_minutes: i64\",
+            COUNT(r.id) AS \"rated_episode_count!: i64\",
+            SUM(r.average_rating * r.num_votes) / SUM(r.num_votes) AS \"average_episode_rating: f64\"
+        FROM episodes e
+        LEFT JOIN titles t ON t.id = e.id
+        LEFT JOIN ratings r ON r.id = e.id
+        WHERE e.parent_id = ?",
+        id
+    )
+    .fetch_one(pool)
+    .await?;
+
+    let highest_rated_episode = query_as!(
+        Episode,
+        "SELECT 
+        e.id, e.parent_id, e.season_number as \"season_number: _\", e.episode_number as \"episode_number: _\", e.absolute_number
+        FROM episodes e
+        JOIN ratings r ON r.id = e.id
+        WHERE e.parent_id = ?
+        ORDER BY r.average_rating DESC, r.num_votes DESC
+        LIMIT 1",
+        id
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    let lowest_rated_episode = query_as!(
+        Episode,
+        "SELECT 
+        e.id, e.parent_id, e.season_number as \"season_number: _\", e.episode_number as \"episode_number: _\", e.absolute_number
+        FROM episodes e
+        JOIN ratings r ON r.id = e.id
+        WHERE e.parent_id = ?
+        ORDER BY r.average_rating ASC, r.num_votes DESC
+        LIMIT 1",
+        id
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(SeriesStats {
+        episode_count: totals.episode_count,
+        season_count: totals.season_count,
+        total_runtime_minutes: totals.total_runtime_minutes,
+        rated_episode_count: totals.rated_episode_count,
+        average_episode_rating: totals.average_episode_rating.map(|r| r as f32),
+        highest_rated_episode,
+        lowest_rated_episode,
+    })
+}
@@ -0,0 +1,77 @@
+use super::title::TitleData;
+use crate::{id::Id, kind::TitleKind};
+use sqlx::{QueryBuilder, Row, SqlitePool};
+
+/// Best rated titles to consider as candidates
+const MAX_CANDIDATES: i64 = 5000;
+
+/// Find titles similar to the given one, returning title IDs with their similarity score, best first.
+///
+/// Candidates are the `MAX_CANDIDATES` titles with the highest weighted rating in `chart_scores`, so unrated
+/// titles are never suggested, and only a fixed number of titles is checked for shared genres. Candidates must
+/// share at least one genre and are scored by:
+/// - the fraction of this title's genres they share
+/// - whether they are the same kind
+/// - how close their years are, with overlapping runs scoring highest
+/// - their weighted rating
+///
+/// Principal cast and crew would be a strong signal too, but hubble does not import `title.principals.tsv.gz` yet.
+pub async fn get_similar(
+    pool: &SqlitePool,
+    title: &TitleData,
+    limit: i64,
+) -> Result<Vec<(Id, f32)>, sqlx::Error> {
+    if title.genres.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut qb = QueryBuilder::new(
+        "WITH candidates AS (SELECT c.id, c.weighted_rating FROM chart_scores c WHERE c.id != ",
+    );
+    qb.push_bind(title.id.get());
+
+    // episodes are only similar to other episodes
+    let episode = TitleKind::TvEpisode as i64;
+    if title.kind == TitleKind::TvEpisode {
+        qb.push(" AND c.type = ").push_bind(episode);
+    } else {
+        qb.push(" AND c.type != ").push_bind(episode);
+    }
+
+    qb.push(" ORDER BY c.weighted_rating DESC LIMIT ")
+        .push_bind(MAX_CANDIDATES)
+        .push(
+            "), shared AS MATERIALIZED (
+                SELECT ca.id, ca.weighted_rating, (
+                    SELECT COUNT(*) FROM title_genres tg WHERE tg.title_id = ca.id AND tg.genre_id IN (",
+        );
+    let mut separated = qb.separated(", ");
+    for genre in &title.genres {
+        separated.push_bind(*genre as i64);
+    }
+    qb.push(")) AS count FROM candidates ca) SELECT s.id, (s.count");
+
+    let start_year = title.start_year;
+    let end_year = title.end_year.or(title.start_year);
+    qb.push(" * 3.0 / ")
+        .push_bind(title.genres.len() as i64)
+        .push(" + (t.type = ")
+        .push_bind(title.kind as i64)
+        .push(") + COALESCE(MAX(0.0, 1.0 - MAX(0, MAX(t.start_year, ")
+        .push_bind(start_year)
+        .push(") - MIN(COALESCE(t.end_year, t.start_year), ")
+        .push_bind(end_year)
+        .push(")) / 10.0), 0.0) + (s.weighted_rating - 5.0) / 5.0) AS score")
+        .push(" FROM shared s JOIN titles t ON t.id = s.id WHERE s.count > 0")
+        .push(" ORDER BY score DESC LIMIT ")
+        .push_bind(limit);
+
+    let rows = qb.build().fetch_all(pool).await?;
+    rows.into_iter()
+        .map(|row| {
+            let id: i64 = row.try_get("id")?;
+            let score: f64 = row.try_get("score")?;
+            Ok((id.into(), score as f32))
+        })
+        .collect()
+}
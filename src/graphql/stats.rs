@@ -0,0 +1,144 @@
+use super::facets::{KindFacet, RatingBandFacet};
+use crate::sync::{get_file_metas, DataDir};
+use async_graphql::{Context, Object, Result, SimpleObject};
+use chrono::{DateTime, Utc};
+use sqlx::{query, query_as, query_scalar, SqlitePool};
+
+/// Dataset-wide numbers, every field is only computed when selected.
+/// Counting akas and titles scans tens of millions of rows, so expect these to take a few seconds.
+/// There is no count of people, since hubble doesn't import `name.basics.tsv.gz`.
+pub struct Stats;
+
+#[derive(SimpleObject)]
+pub struct YearCount {
+    pub year: i32,
+    pub count: i64,
+}
+
+#[derive(SimpleObject)]
+pub struct DatasetFile {
+    /// e.g. "title.basics.tsv.gz"
+    pub name: String,
+    pub downloaded_at: Option<DateTime<Utc>>,
+    /// The Last-Modified header IMDb sent with the file
+    pub last_modified: Option<DateTime<Utc>>,
+    pub etag: Option<String>,
+    /// Hex SHA-256 of the downloaded file
+    pub sha256: Option<String>,
+    /// Null if the file was downloaded but not imported yet
+    pub imported_at: Option<DateTime<Utc>>,
+    /// The error from the last failed sync of this file, null once it syncs again
+    pub last_error: Option<String>,
+    pub failed_at: Option<DateTime<Utc>>,
+    /// Rows that failed to parse in the last import, samples are kept in "<name>.rejected.jsonl"
+    pub rejected_rows: Option<i64>,
+}
+
+#[Object]
+impl Stats {
+    async fn titles(&self, ctx: &Context<'_>) -> Result<i64> {
+        let pool = ctx.data::<SqlitePool>()?;
+        Ok(query_scalar!("SELECT COUNT(*) FROM titles")
+            .fetch_one(pool)
+            .await?)
+    }
+
+    /// Number of titles per kind, most common first
+    async fn kinds(&self, ctx: &Context<'_>) -> Result<Vec<KindFacet>> {
+        let pool = ctx.data::<SqlitePool>()?;
+        let rows = query!(
+            "SELECT type AS kind, COUNT(*) AS count FROM titles GROUP BY type ORDER BY count DESC"
+        )
+        .fetch_all(pool)
+        .await?;
+        Ok(rows
+            .into_iter()
+            .map(|row| KindFacet {
+                kind: row.kind.into(),
+                count: row.count,
+            })
+            .collect())
+    }
+
+    async fn episodes(&self, ctx: &Context<'_>) -> Result<i64> {
+        let pool = ctx.data::<SqlitePool>()?;
+        Ok(query_scalar!("SELECT COUNT(*) FROM episodes")
+            .fetch_one(pool)
+            .await?)
+    }
+
+    async fn akas(&self, ctx: &Context<'_>) -> Result<i64> {
+        let pool = ctx.data::<SqlitePool>()?;
+        Ok(query_scalar!("SELECT COUNT(*) FROM akas")
+            .fetch_one(pool)
+            .await?)
+    }
+
+    async fn ratings(&self, ctx: &Context<'_>) -> Result<i64> {
+        let pool = ctx.data::<SqlitePool>()?;
+        Ok(query_scalar!("SELECT COUNT(*) FROM ratings")
+            .fetch_one(pool)
+            .await?)
+    }
+
+    /// Number of rated titles per whole-number rating band
+    async fn rating_histogram(&self, ctx: &Context<'_>) -> Result<Vec<RatingBandFacet>> {
+        let pool = ctx.data::<SqlitePool>()?;
+        let rows = query_as!(
+            RatingBandFacet,
+            "SELECT CAST(average_rating AS INTEGER) AS \"min!: i32\", MIN(CAST(average_rating AS INTEGER) + 1, 10) AS \"max!: i32\", COUNT(*) AS count
+            FROM ratings
+            GROUP BY 1
+            ORDER BY 1"
+        )
+        .fetch_all(pool)
+        .await?;
+        Ok(rows)
+    }
+
+    /// Number of titles per start year, titles without a year are left out
+    async fn year_histogram(&self, ctx: &Context<'_>) -> Result<Vec<YearCount>> {
+        let pool = ctx.data::<SqlitePool>()?;
+        let rows = query_as!(
+            YearCount,
+            "SELECT start_year AS \"year!: i32\", COUNT(*) AS count
+            FROM titles
+            WHERE start_year IS NOT NULL
+            GROUP BY start_year
+            ORDER BY start_year"
+        )
+        .fetch_all(pool)
+        .await?;
+        Ok(rows)
+    }
+
+    /// IMDb doesn't version its datasets, so this is the newest `lastModified` of all files
+    async fn dataset_version(&self, ctx: &Context<'_>) -> Result<Option<DateTime<Utc>>> {
+        let data_dir = ctx.data::<DataDir>()?;
+        let metas = get_file_metas(&data_dir.0)?;
+        Ok(metas.iter().filter_map(|meta| meta.last_modified).max())
+    }
+
+    async fn files(&self, ctx: &Context<'_>) -> Result<Vec<DatasetFile>> {
+        let data_dir = ctx.data::<DataDir>()?;
+        let metas = get_file_metas(&data_dir.0)?;
+        Ok(metas
+            .into_iter()
+            .map(|meta| DatasetFile {
+                name: meta
+                    .path
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_default(),
+                downloaded_at: meta.downloaded_at,
+                last_modified: meta.last_modified,
+                etag: meta.etag,
+                sha256: meta.sha256,
+                imported_at: meta.imported_at,
+                last_error: meta.last_error,
+                failed_at: meta.failed_at,
+                rejected_rows: meta.rejected_rows.map(|count| count as i64),
+            })
+            .collect())
+    }
+}
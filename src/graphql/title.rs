@@ -1,48 +1,51 @@
 use std::collections::HashMap;
 use std::sync::Arc;
 
-use super::episode::Episode;
-use crate::{id::Id, kind::TitleKind};
+use super::aka::{Aka, AkaRow, AkaType};
+use super::episode::{get_episode, Episode};
+use super::series::{
+    get_episode_gaps, get_series_stats, group_seasons, Season, SeasonGaps, SeriesStats,
+};
+use super::similar::get_similar;
+use crate::{
+    genre::Genre,
+    id::{Id, NodeId},
+    kind::TitleKind,
+};
 use async_graphql::*;
-use dataloader::Loader;
+use dataloader::{DataLoader, Loader};
 use itertools::Itertools;
-use sqlx::{query, query_as, sqlite::SqliteRow, FromRow, SqlitePool};
+use sqlx::{query_as, query_scalar, sqlite::SqliteRow, FromRow, SqlitePool};
 use sqlx::{QueryBuilder, Row};
 
-#[derive(SimpleObject, Clone)]
-#[graphql(complex)]
-pub struct Title {
+/// Fields shared by every kind of title, see the `Title` interface.
+#[derive(Clone)]
+pub struct TitleData {
     pub id: Id,
     pub kind: TitleKind,
+    /// IMDb's type name for titles of kind `Other`
+    pub type_name: Option<String>,
     pub primary_title: String,
     pub original_title: Option<String>,
     pub is_adult: bool,
     pub start_year: Option<i32>,
     pub end_year: Option<i32>,
     pub runtime_minutes: Option<i32>,
-    pub genres: Vec<String>,
+    pub genres: Vec<Genre>,
+    /// Request-level locale used by `localized_title` when no arguments are given
+    pub locale: Option<Locale>,
 }
 
-#[ComplexObject]
-impl Title {
-    async fn episodes(&self, ctx: &Context<'_>) -> Result<Vec<Episode>> {
-        let pool = ctx.data::<SqlitePool>()?;
-        let id = self.id.get();
-        let episodes = query_as!(
-            Episode,
-            "SELECT 
-            id, parent_id, season_number as \"season_number: _\", episode_number as \"episode_number: _\"
-            FROM episodes
-            WHERE parent_id = ?",
-            id
-        )
-        .fetch_all(pool)
-        .await?;
-
-        Ok(episodes)
+impl TitleData {
+    /// Null for `Other` titles imported before their type name was stored
+    pub fn title_type(&self) -> Option<String> {
+        match self.kind.imdb_name() {
+            Some(name) => Some(name.to_string()),
+            None => self.type_name.clone(),
+        }
     }
 
-    async fn rating(&self, ctx: &Context<'_>) -> Result<Option<Rating>> {
+    pub async fn rating(&self, ctx: &Context<'_>) -> Result<Option<Rating>> {
         let pool = ctx.data::<SqlitePool>()?;
         let id = self.id.get();
         let rating = query_as!(
@@ -59,83 +62,494 @@ impl Title {
         Ok(rating)
     }
 
-    async fn akas(&self, ctx: &Context<'_>) -> Result<Vec<Aka>> {
+    pub async fn akas(
+        &self,
+        ctx: &Context<'_>,
+        region: Option<String>,
+        language: Option<String>,
+        kind: Option<AkaType>,
+        include_primary_title: bool,
+    ) -> Result<Vec<Aka>> {
         let pool = ctx.data::<SqlitePool>()?;
         let id = self.id.get();
-        let akas = query!(
+        let akas = query_as!(
+            AkaRow,
             "SELECT 
-            id, ordering, title, region, language, types, attributes, is_original_title
+            ordering, title, region, language, types, attributes, is_original_title AS \"is_original_title: bool\"
             FROM akas
-            WHERE id = ? AND title != ?",
+            WHERE id = ?1
+                AND (?2 OR title != ?3)
+                AND (?4 IS NULL OR region = ?4)
+                AND (?5 IS NULL OR language = ?5)",
             id,
-            self.primary_title
+            include_primary_title,
+            self.primary_title,
+            region,
+            language
         )
         .fetch_all(pool)
         .await?;
 
         Ok(akas
             .into_iter()
-            .map(|aka| Aka {
-                ordering: aka.ordering,
-                title: aka.title,
-                region: aka.region,
-                language: aka.language,
-                attributes: aka
-                    .attributes
-                    .map(|a| a.split(",").map(|s| s.to_string()).collect())
-                    .unwrap_or_default(),
-                types: aka
-                    .types
-                    .map(|t| t.split(",").map(|s| s.to_string()).collect())
-                    .unwrap_or_default(),
-                is_original_title: aka.is_original_title == 1,
-            })
+            .map(|row| Aka::from_row(self.id, row))
+            .filter(|aka| kind.is_none_or(|kind| aka.has_type(kind)))
             .collect_vec())
     }
+
+    pub async fn similar(
+        &self,
+        ctx: &Context<'_>,
+        limit: Option<usize>,
+    ) -> Result<Vec<TitleWithRank>> {
+        let pool = ctx.data::<SqlitePool>()?;
+        let limit = limit.unwrap_or(10).min(100) as i64;
+        let similar = get_similar(pool, self, limit).await?;
+        let loader = ctx.data::<DataLoader<TitleLoader>>()?;
+        TitleWithRank::load_ranked(loader, similar, self.locale.clone()).await
+    }
+
+    pub async fn localized_title(
+        &self,
+        ctx: &Context<'_>,
+        region: Option<String>,
+        language: Option<String>,
+    ) -> Result<String> {
+        let (region, language) = match (region, language, &self.locale) {
+            (None, None, Some(locale)) => (locale.region.clone(), locale.language.clone()),
+            (region, language, _) => (region, language),
+        };
+
+        if region.is_none() && language.is_none() {
+            return Ok(self.primary_title.clone());
+        }
+
+        let pool = ctx.data::<SqlitePool>()?;
+        let title = get_localized_title(pool, self.id, region, language).await?;
+        Ok(title.unwrap_or_else(|| self.primary_title.clone()))
+    }
+}
+
+/// The aka to show for a region and/or language. akas from the exact region come first, and within them
+/// imdbDisplay akas, which are what IMDb itself shows there. Then akas in the requested language, with akas
+/// in a different language ranked last.
+async fn get_localized_title(
+    pool: &SqlitePool,
+    id: Id,
+    region: Option<String>,
+    language: Option<String>,
+) -> Result<Option<String>, sqlx::Error> {
+    let id = id.get();
+    query_scalar!(
+        "SELECT title
+        FROM akas
+        WHERE id = ?1 AND (region = ?2 OR language = ?3)
+        ORDER BY
+            COALESCE(region = ?2, 0) DESC,
+            COALESCE(types LIKE '%imdbDisplay%', 0) DESC,
+            COALESCE(language = ?3, 0) DESC,
+            COALESCE(language != ?3, 0) ASC,
+            ordering ASC
+        LIMIT 1",
+        id,
+        region,
+        language
+    )
+    .fetch_optional(pool)
+    .await
+}
+
+/// Implement the `Title` interface fields for a type with a `data: TitleData` field,
+/// followed by any fields specific to that type.
+macro_rules! title_object {
+    ($ty:ident, { $($fields:tt)* }) => {
+        #[Object]
+        impl $ty {
+            pub async fn id(&self, _ctx: &Context<'_>) -> Result<ID> {
+                Ok(NodeId::Title(self.data.id).into())
+            }
+
+            async fn kind(&self, _ctx: &Context<'_>) -> Result<TitleKind> {
+                Ok(self.data.kind)
+            }
+
+            /// IMDb's `titleType`, e.g. "tvSeries". Types added by IMDb after this version of hubble
+            /// have kind `OTHER` and are only told apart here.
+            async fn title_type(&self, _ctx: &Context<'_>) -> Result<Option<String>> {
+                Ok(self.data.title_type())
+            }
+
+            async fn primary_title(&self, _ctx: &Context<'_>) -> Result<String> {
+                Ok(self.data.primary_title.clone())
+            }
+
+            async fn original_title(&self, _ctx: &Context<'_>) -> Result<Option<String>> {
+                Ok(self.data.original_title.clone())
+            }
+
+            async fn is_adult(&self, _ctx: &Context<'_>) -> Result<bool> {
+                Ok(self.data.is_adult)
+            }
+
+            async fn start_year(&self, _ctx: &Context<'_>) -> Result<Option<i32>> {
+                Ok(self.data.start_year)
+            }
+
+            async fn end_year(&self, _ctx: &Context<'_>) -> Result<Option<i32>> {
+                Ok(self.data.end_year)
+            }
+
+            async fn runtime_minutes(&self, _ctx: &Context<'_>) -> Result<Option<i32>> {
+                Ok(self.data.runtime_minutes)
+            }
+
+            async fn genres(&self, _ctx: &Context<'_>) -> Result<Vec<Genre>> {
+                Ok(self.data.genres.clone())
+            }
+
+            async fn rating(&self, ctx: &Context<'_>) -> Result<Option<Rating>> {
+                self.data.rating(ctx).await
+            }
+
+            async fn akas(
+                &self,
+                ctx: &Context<'_>,
+                region: Option<String>,
+                language: Option<String>,
+                #[graphql(name = "type")] kind: Option<AkaType>,
+                #[graphql(default = false)] include_primary_title: bool,
+            ) -> Result<Vec<Aka>> {
+                self.data
+                    .akas(ctx, region, language, kind, include_primary_title)
+                    .await
+            }
+
+            /// Titles similar to this one by genre, kind, year and rating, at most 100. `rank` is the similarity score.
+            async fn similar(
+                &self,
+                ctx: &Context<'_>,
+                limit: Option<usize>,
+            ) -> Result<Vec<TitleWithRank>> {
+                self.data.similar(ctx, limit).await
+            }
+
+            /// The best title for the given region and/or language, falling back to the primary title.
+            /// If neither is given, the locale passed to `Query.titles` is used instead. It carries over to
+            /// `similar` and `series` of those titles, but not to episodes or titles from other queries.
+            async fn localized_title(
+                &self,
+                ctx: &Context<'_>,
+                region: Option<String>,
+                language: Option<String>,
+            ) -> Result<String> {
+                self.data.localized_title(ctx, region, language).await
+            }
+
+            $($fields)*
+        }
+    };
+}
+
+/// A movie, short, series, episode etc. The concrete type depends on `kind`.
+#[allow(clippy::duplicated_attributes)] // fields with the same type look like duplicates to clippy
+#[derive(Interface, Clone)]
+#[graphql(
+    field(name = "id", ty = "ID"),
+    field(name = "kind", ty = "TitleKind"),
+    field(name = "title_type", ty = "Option<String>"),
+    field(name = "primary_title", ty = "String"),
+    field(name = "original_title", ty = "Option<String>"),
+    field(name = "is_adult", ty = "bool"),
+    field(name = "start_year", ty = "Option<i32>"),
+    field(name = "end_year", ty = "Option<i32>"),
+    field(name = "runtime_minutes", ty = "Option<i32>"),
+    field(name = "genres", ty = "Vec<Genre>"),
+    field(name = "rating", ty = "Option<Rating>"),
+    field(
+        name = "akas",
+        ty = "Vec<Aka>",
+        arg(name = "region", ty = "Option<String>"),
+        arg(name = "language", ty = "Option<String>"),
+        arg(name = "type", ty = "Option<AkaType>"),
+        arg(name = "include_primary_title", ty = "bool", default = false)
+    ),
+    field(
+        name = "similar",
+        ty = "Vec<TitleWithRank>",
+        arg(name = "limit", ty = "Option<usize>")
+    ),
+    field(
+        name = "localized_title",
+        ty = "String",
+        arg(name = "region", ty = "Option<String>"),
+        arg(name = "language", ty = "Option<String>")
+    )
+)]
+pub enum Title {
+    Movie(Movie),
+    Series(Series),
+    Episode(EpisodeTitle),
+    Short(Short),
+    VideoGame(VideoGame),
+    Other(OtherTitle),
+}
+
+impl From<TitleData> for Title {
+    fn from(data: TitleData) -> Self {
+        match data.kind {
+            TitleKind::Movie | TitleKind::TvMovie => Title::Movie(Movie { data }),
+            TitleKind::TvSeries | TitleKind::TvMiniSeries => Title::Series(Series { data }),
+            TitleKind::TvEpisode => Title::Episode(EpisodeTitle { data }),
+            TitleKind::Short | TitleKind::TvShort => Title::Short(Short { data }),
+            TitleKind::VideoGame => Title::VideoGame(VideoGame { data }),
+            TitleKind::TvPilot | TitleKind::TvSpecial | TitleKind::Video | TitleKind::Other => {
+                Title::Other(OtherTitle { data })
+            }
+        }
+    }
+}
+
+/// Movies and TV movies
+#[derive(Clone)]
+pub struct Movie {
+    pub data: TitleData,
+}
+
+title_object!(Movie, {});
+
+/// TV series and mini series
+#[derive(Clone)]
+pub struct Series {
+    pub data: TitleData,
+}
+
+title_object!(Series, {
+    /// Every episode in airing order, episodes without numbers come last
+    async fn episodes(&self, ctx: &Context<'_>) -> Result<Vec<Episode>> {
+        let pool = ctx.data::<SqlitePool>()?;
+        let id = self.data.id.get();
+        let episodes = query_as!(
+            Episode,
+            "SELECT 
+            e.id, e.parent_id, e.season_number as \"season_number: _\", e.episode_number as \"episode_number: _\", e.absolute_number
+            FROM episodes e
+            LEFT JOIN titles t ON t.id = e.id
+            WHERE e.parent_id = ?
+            ORDER BY e.season_number NULLS LAST, e.episode_number NULLS LAST, t.start_year, e.id",
+            id
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(episodes)
+    }
+
+    /// Aggregates over every episode
+    async fn series_stats(&self, ctx: &Context<'_>) -> Result<SeriesStats> {
+        let pool = ctx.data::<SqlitePool>()?;
+        Ok(get_series_stats(pool, self.data.id).await?)
+    }
+
+    async fn seasons(&self, ctx: &Context<'_>) -> Result<Vec<Season>> {
+        let episodes = self.episodes(ctx).await?;
+        Ok(group_seasons(episodes))
+    }
+
+    /// Missing episode numbers for each season, for spotting incomplete numbering.
+    async fn episode_gaps(&self, ctx: &Context<'_>) -> Result<Vec<SeasonGaps>> {
+        let pool = ctx.data::<SqlitePool>()?;
+        Ok(get_episode_gaps(pool, self.data.id).await?)
+    }
+});
+
+/// A single episode of a series
+#[derive(Clone)]
+pub struct EpisodeTitle {
+    pub data: TitleData,
+}
+
+title_object!(EpisodeTitle, {
+    async fn series(&self, ctx: &Context<'_>) -> Result<Option<Series>> {
+        let Some(episode) = self.episode(ctx).await? else {
+            return Ok(None);
+        };
+
+        let loader = ctx.data::<DataLoader<TitleLoader>>()?;
+        let series = loader.load_one(episode.parent_id).await?;
+        Ok(series.map(|mut data| {
+            data.locale = self.data.locale.clone();
+            Series { data }
+        }))
+    }
+
+    /// The season and episode number of this episode
+    async fn episode(&self, ctx: &Context<'_>) -> Result<Option<Episode>> {
+        let pool = ctx.data::<SqlitePool>()?;
+        Ok(get_episode(pool, self.data.id).await?)
+    }
+});
+
+/// Shorts and TV shorts
+#[derive(Clone)]
+pub struct Short {
+    pub data: TitleData,
+}
+
+title_object!(Short, {});
+
+#[derive(Clone)]
+pub struct VideoGame {
+    pub data: TitleData,
+}
+
+title_object!(VideoGame, {});
+
+/// Pilots, TV specials, videos and types IMDb added after this version of hubble
+#[derive(Clone)]
+pub struct OtherTitle {
+    pub data: TitleData,
 }
 
-impl FromRow<'_, SqliteRow> for Title {
+title_object!(OtherTitle, {});
+
+impl FromRow<'_, SqliteRow> for TitleData {
     fn from_row(row: &SqliteRow) -> std::result::Result<Self, sqlx::Error> {
         let id: i64 = row.try_get("id")?;
+        let kind: i64 = row.try_get("kind")?;
         let genres: Option<String> = row.try_get("genres")?;
         Ok(Self {
             id: id.into(),
-            kind: row.try_get("kind")?,
+            kind: kind.into(),
+            type_name: row.try_get("type_name")?,
             primary_title: row.try_get("primary_title")?,
             original_title: row.try_get("original_title")?,
             is_adult: row.try_get("is_adult")?,
             start_year: row.try_get("start_year")?,
             end_year: row.try_get("end_year")?,
             runtime_minutes: row.try_get("runtime_minutes")?,
-            genres: genres
-                .map(|s| s.split(',').map(|s| s.to_string()).collect())
-                .unwrap_or_default(),
+            genres: genres.as_deref().map(Genre::parse_list).unwrap_or_default(),
+            locale: None,
         })
     }
 }
 
+#[derive(InputObject, Clone, Debug)]
+pub struct Locale {
+    /// ISO 3166 region code as used by IMDb, e.g. "DE" or "BR"
+    pub region: Option<String>,
+    /// ISO 639 language code as used by IMDb, e.g. "de" or "pt"
+    pub language: Option<String>,
+}
+
 #[derive(SimpleObject, Clone)]
 pub struct Rating {
     pub num_votes: u64,
     pub average_rating: f32,
 }
 
-#[derive(SimpleObject, Clone)]
-pub struct Aka {
-    pub ordering: i64,
-    pub title: String,
-    pub region: Option<String>,
-    pub language: Option<String>,
-    pub types: Vec<String>,
-    pub attributes: Vec<String>,
-    pub is_original_title: bool,
-}
-
-#[derive(SimpleObject)]
+/// A title with a score, like its search relevance or its similarity to another title
 pub struct TitleWithRank {
-    #[graphql(flatten)]
-    pub title: Title,
     pub rank: Option<f32>,
+    pub data: TitleData,
+}
+
+/// The title fields are repeated from before `title` existed, for clients that still read them here.
+#[Object]
+impl TitleWithRank {
+    async fn rank(&self) -> Option<f32> {
+        self.rank
+    }
+
+    async fn title(&self) -> Title {
+        self.data.clone().into()
+    }
+
+    #[graphql(deprecation = "Use `title { id }`")]
+    async fn id(&self) -> ID {
+        NodeId::Title(self.data.id).into()
+    }
+
+    #[graphql(deprecation = "Use `title { kind }`")]
+    async fn kind(&self) -> TitleKind {
+        self.data.kind
+    }
+
+    #[graphql(deprecation = "Use `title { primaryTitle }`")]
+    async fn primary_title(&self) -> &str {
+        &self.data.primary_title
+    }
+
+    #[graphql(deprecation = "Use `title { originalTitle }`")]
+    async fn original_title(&self) -> Option<&str> {
+        self.data.original_title.as_deref()
+    }
+
+    #[graphql(deprecation = "Use `title { isAdult }`")]
+    async fn is_adult(&self) -> bool {
+        self.data.is_adult
+    }
+
+    #[graphql(deprecation = "Use `title { startYear }`")]
+    async fn start_year(&self) -> Option<i32> {
+        self.data.start_year
+    }
+
+    #[graphql(deprecation = "Use `title { endYear }`")]
+    async fn end_year(&self) -> Option<i32> {
+        self.data.end_year
+    }
+
+    #[graphql(deprecation = "Use `title { runtimeMinutes }`")]
+    async fn runtime_minutes(&self) -> Option<i32> {
+        self.data.runtime_minutes
+    }
+
+    #[graphql(deprecation = "Use `title { genres }`")]
+    async fn genres(&self) -> &[Genre] {
+        &self.data.genres
+    }
+
+    #[graphql(deprecation = "Use `title { rating }`")]
+    async fn rating(&self, ctx: &Context<'_>) -> Result<Option<Rating>> {
+        self.data.rating(ctx).await
+    }
+
+    #[graphql(deprecation = "Use `title { akas }`")]
+    async fn akas(&self, ctx: &Context<'_>) -> Result<Vec<Aka>> {
+        self.data.akas(ctx, None, None, None, false).await
+    }
+
+    /// Empty for titles that aren't series
+    #[graphql(deprecation = "Use `title { ... on Series { episodes } }`")]
+    async fn episodes(&self, ctx: &Context<'_>) -> Result<Vec<Episode>> {
+        match Title::from(self.data.clone()) {
+            Title::Series(series) => series.episodes(ctx).await,
+            _ => Ok(Vec::new()),
+        }
+    }
+}
+
+impl TitleWithRank {
+    /// Load titles for ranked IDs, keeping their order. IDs that no longer exist are skipped.
+    pub async fn load_ranked(
+        loader: &DataLoader<TitleLoader>,
+        ranked: Vec<(Id, f32)>,
+        locale: Option<Locale>,
+    ) -> Result<Vec<TitleWithRank>> {
+        let mut titles = loader.load_many(ranked.iter().map(|(id, _)| *id)).await?;
+        Ok(ranked
+            .into_iter()
+            .filter_map(|(id, rank)| {
+                titles.remove(&id).map(|mut data| {
+                    data.locale = locale.clone();
+                    TitleWithRank {
+                        rank: Some(rank),
+                        data,
+                    }
+                })
+            })
+            .collect())
+    }
 }
 
 pub struct TitleLoader {
@@ -149,11 +563,11 @@ impl TitleLoader {
 }
 
 impl Loader<Id> for TitleLoader {
-    type Value = Title;
+    type Value = TitleData;
     type Error = Arc<sqlx::Error>;
 
     async fn load(&self, keys: &[Id]) -> Result<HashMap<Id, Self::Value>, Self::Error> {
-        let mut query_builder = QueryBuilder::new("SELECT id, type AS kind, primary_title, original_title, start_year, end_year, is_adult, genres, runtime_minutes FROM titles WHERE id IN (");
+        let mut query_builder = QueryBuilder::new("SELECT id, type AS kind, type_name, primary_title, original_title, start_year, end_year, is_adult, genres, runtime_minutes FROM titles WHERE id IN (");
 
         let mut is_first = true;
         for id in keys.iter() {
@@ -167,12 +581,68 @@ impl Loader<Id> for TitleLoader {
         }
 
         query_builder.push(")");
-        let query = query_builder.build_query_as::<Title>();
+        let query = query_builder.build_query_as::<TitleData>();
         let titles = query.fetch_all(&self.pool).await?;
 
-        Ok(titles
-            .into_iter()
-            .map(|title| (title.id.clone(), title))
-            .collect())
+        Ok(titles.into_iter().map(|title| (title.id, title)).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    #[tokio::test]
+    async fn prefers_the_exact_region_over_display_akas() {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        sqlx::migrate!("./migrations").run(&pool).await.unwrap();
+        sqlx::query("INSERT INTO titles (id, type, primary_title, is_adult) VALUES (1, 0, 'City of God', 0)")
+            .execute(&pool)
+            .await
+            .unwrap();
+        let akas = [
+            (1, "Cidade de Deus (PT)", "PT", "pt", "imdbDisplay"),
+            (2, "Cidade de Deus (BR working)", "BR", "pt", "working"),
+            (3, "Cidade de Deus (BR)", "BR", "pt", "imdbDisplay"),
+        ];
+        for (ordering, title, region, language, types) in akas {
+            sqlx::query("INSERT INTO akas (id, ordering, title, region, language, types, is_original_title) VALUES (1, ?, ?, ?, ?, ?, 0)")
+                .bind(ordering)
+                .bind(title)
+                .bind(region)
+                .bind(language)
+                .bind(types)
+                .execute(&pool)
+                .await
+                .unwrap();
+        }
+
+        let localized = |region: Option<&str>, language: Option<&str>| {
+            get_localized_title(
+                &pool,
+                Id::from(1i64),
+                region.map(Into::into),
+                language.map(Into::into),
+            )
+        };
+        let title = localized(Some("BR"), Some("pt")).await.unwrap();
+        assert_eq!(title.as_deref(), Some("Cidade de Deus (BR)"));
+        let title = localized(None, Some("pt")).await.unwrap();
+        assert_eq!(title.as_deref(), Some("Cidade de Deus (PT)"));
+        let title = localized(Some("US"), None).await.unwrap();
+        assert_eq!(title, None);
+
+        // Brazil's own aka still wins over one IMDb displays in Portugal
+        sqlx::query("DELETE FROM akas WHERE ordering = 3")
+            .execute(&pool)
+            .await
+            .unwrap();
+        let title = localized(Some("BR"), Some("pt")).await.unwrap();
+        assert_eq!(title.as_deref(), Some("Cidade de Deus (BR working)"));
     }
 }
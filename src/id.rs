@@ -1,4 +1,5 @@
-use std::fmt::Debug;
+use std::fmt::{Debug, Display};
+use std::str::FromStr;
 
 use async_graphql::*;
 use serde::{Deserialize, Serialize};
@@ -12,9 +13,23 @@ impl Id {
     pub fn get(&self) -> u32 {
         self.0
     }
+}
 
-    pub fn to_string(&self) -> String {
-        format!("tt{:07}", self.0)
+impl Display for Id {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "tt{:07}", self.0)
+    }
+}
+
+impl FromStr for Id {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        value
+            .strip_prefix("tt")
+            .and_then(|value| value.parse().ok())
+            .map(Id)
+            .ok_or_else(|| format!("Invalid IMDb ID \"{}\"", value))
     }
 }
 
@@ -22,18 +37,7 @@ impl Id {
 impl ScalarType for Id {
     fn parse(value: Value) -> InputValueResult<Self> {
         if let Value::String(value) = &value {
-            if value.starts_with("tt") {
-                let value = value[2..].parse().map_err(|_| {
-                    InputValueError::custom(format!("Invalid IMDb ID \"{}\"", value))
-                })?;
-
-                Ok(Id(value))
-            } else {
-                Err(InputValueError::custom(format!(
-                    "Invalid IMDb ID \"{}\"",
-                    value
-                )))
-            }
+            value.parse().map_err(InputValueError::custom)
         } else {
             Err(InputValueError::expected_type(value))
         }
@@ -61,14 +65,13 @@ impl<'de> Deserialize<'de> for Id {
         D: serde::Deserializer<'de>,
     {
         let s = String::deserialize(deserializer)?;
-        let s = s[2..].parse().map_err(serde::de::Error::custom)?;
-        Ok(Id(s))
+        s.parse().map_err(serde::de::Error::custom)
     }
 }
 
 impl Debug for Id {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "Id({})", self.to_string())
+        write!(f, "Id({})", self)
     }
 }
 
@@ -83,3 +86,43 @@ impl From<i64> for Id {
         Id(id as u32)
     }
 }
+
+/// Globally unique ID for anything implementing the `Node` interface, exposed as a GraphQL `ID`.
+/// Titles use their IMDb ID as is, other nodes are prefixed with their type.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum NodeId {
+    /// "tt0000001"
+    Title(Id),
+    /// "episode:tt0000001"
+    Episode(Id),
+    /// "aka:tt0000001:2", the ID of the title and the ordering of the aka
+    Aka(Id, i64),
+}
+
+impl Display for NodeId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NodeId::Title(id) => write!(f, "{}", id),
+            NodeId::Episode(id) => write!(f, "episode:{}", id),
+            NodeId::Aka(id, ordering) => write!(f, "aka:{}:{}", id, ordering),
+        }
+    }
+}
+
+impl FromStr for NodeId {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid node ID \"{}\"", value);
+        let parts: Vec<&str> = value.split(':').collect();
+        match parts.as_slice() {
+            [id] => Ok(NodeId::Title(id.parse()?)),
+            ["episode", id] => Ok(NodeId::Episode(id.parse()?)),
+            ["aka", id, ordering] => Ok(NodeId::Aka(
+                id.parse()?,
+                ordering.parse().map_err(|_| invalid())?,
+            )),
+            _ => Err(invalid()),
+        }
+    }
+}
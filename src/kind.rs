@@ -1,31 +1,60 @@
 use async_graphql::Enum;
-use serde::Deserialize;
 
-#[derive(Enum, Copy, Clone, Eq, PartialEq, Deserialize, Debug, sqlx::Type)]
+/// The discriminants are the values stored in `titles.type`.
+#[derive(Enum, Copy, Clone, Eq, PartialEq, Debug)]
 #[repr(i64)]
 pub enum TitleKind {
-    #[serde(rename = "movie")]
     Movie = 0,
-    #[serde(rename = "short")]
     Short = 1,
-    #[serde(rename = "tvEpisode")]
     TvEpisode = 2,
-    #[serde(rename = "tvMiniSeries")]
     TvMiniSeries = 3,
-    #[serde(rename = "tvMovie")]
     TvMovie = 4,
-    #[serde(rename = "tvPilot")]
     TvPilot = 5,
-    #[serde(rename = "tvSeries")]
     TvSeries = 6,
-    #[serde(rename = "tvShort")]
     TvShort = 7,
-    #[serde(rename = "tvSpecial")]
     TvSpecial = 8,
-    #[serde(rename = "video")]
     Video = 9,
-    #[serde(rename = "videoGame")]
     VideoGame = 10,
+    /// A type IMDb added after this version of hubble, see `Title.titleType`
+    Other = 11,
+}
+
+impl TitleKind {
+    /// The kind for IMDb's `titleType`, `None` for types hubble doesn't know about.
+    pub fn from_imdb(value: &str) -> Option<Self> {
+        Some(match value {
+            "movie" => TitleKind::Movie,
+            "short" => TitleKind::Short,
+            "tvEpisode" => TitleKind::TvEpisode,
+            "tvMiniSeries" => TitleKind::TvMiniSeries,
+            "tvMovie" => TitleKind::TvMovie,
+            "tvPilot" => TitleKind::TvPilot,
+            "tvSeries" => TitleKind::TvSeries,
+            "tvShort" => TitleKind::TvShort,
+            "tvSpecial" => TitleKind::TvSpecial,
+            "video" => TitleKind::Video,
+            "videoGame" => TitleKind::VideoGame,
+            _ => return None,
+        })
+    }
+
+    /// IMDb's `titleType`, `None` for `Other` since that covers any unknown type.
+    pub fn imdb_name(self) -> Option<&'static str> {
+        Some(match self {
+            TitleKind::Movie => "movie",
+            TitleKind::Short => "short",
+            TitleKind::TvEpisode => "tvEpisode",
+            TitleKind::TvMiniSeries => "tvMiniSeries",
+            TitleKind::TvMovie => "tvMovie",
+            TitleKind::TvPilot => "tvPilot",
+            TitleKind::TvSeries => "tvSeries",
+            TitleKind::TvShort => "tvShort",
+            TitleKind::TvSpecial => "tvSpecial",
+            TitleKind::Video => "video",
+            TitleKind::VideoGame => "videoGame",
+            TitleKind::Other => return None,
+        })
+    }
 }
 
 impl From<i64> for TitleKind {
@@ -42,7 +71,8 @@ impl From<i64> for TitleKind {
             8 => TitleKind::TvSpecial,
             9 => TitleKind::Video,
             10 => TitleKind::VideoGame,
-            _ => panic!("Invalid title kind"),
+            // written by a newer version, which knows kinds this one doesn't
+            _ => TitleKind::Other,
         }
     }
 }
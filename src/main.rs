@@ -1,30 +1,58 @@
 use async_graphql::dataloader::DataLoader;
 use async_graphql::http::GraphiQLSource;
 use async_graphql::{EmptyMutation, EmptySubscription, Schema};
-use async_graphql_axum::GraphQL;
+use async_graphql_axum::{GraphQLRequest, GraphQLResponse};
 use axum::{
+    extract::State,
     response::{self, IntoResponse},
     routing::get,
     Router,
 };
+use db::Database;
 use graphql::title::TitleLoader;
 use graphql::Query;
-use sqlx::sqlite::{
-    SqliteAutoVacuum, SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions, SqliteSynchronous,
-};
 use std::env;
 use std::error::Error;
-use std::{str::FromStr, time::Duration};
-use sync::sync_data;
+use std::sync::Arc;
+use sync::{run_sync_scheduler, DataDir, DatasetSource, SnapshotTarget, DEFAULT_MAX_REJECTED_RATE};
 use tokio::net::TcpListener;
 use tokio::signal;
 use tracing::info;
 
+mod db;
+mod genre;
 mod graphql;
 mod id;
 mod kind;
+mod normalize;
+mod release;
 mod sync;
 
+type HubbleSchema = Schema<Query, EmptyMutation, EmptySubscription>;
+
+#[derive(Clone)]
+struct AppState {
+    schema: HubbleSchema,
+    db: Arc<Database>,
+}
+
+/// Every request gets the pool of the database version that is live when it starts,
+/// so all of its queries see the same version even if a sync swaps in a new one meanwhile.
+async fn graphql(State(state): State<AppState>, request: GraphQLRequest) -> GraphQLResponse {
+    // keeps the version from being deleted until the request is done
+    let version = state.db.current();
+    let request = request
+        .into_inner()
+        .data(DataLoader::new(
+            TitleLoader::new(version.pool.clone()),
+            tokio::spawn,
+        ))
+        .data(version.pool.clone());
+    let response = state.schema.execute(request).await;
+    drop(version);
+    response.into()
+}
+
 async fn graphiql() -> impl IntoResponse {
     response::Html(GraphiQLSource::build().endpoint("/").finish())
 }
@@ -50,53 +78,53 @@ async fn main() -> Result<(), Box<dyn Error>> {
         )
     })?;
 
-    let pool = {
+    let db = {
         let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
-        let pool = SqlitePoolOptions::new()
-            .max_connections(2) // avoid locking
-            .acquire_timeout(Duration::from_secs(30))
-            .connect_with(
-                // https://briandouglas.ie/sqlite-defaults/
-                SqliteConnectOptions::from_str(&database_url)
-                    .expect("Failed to parse SQLite path")
-                    .journal_mode(SqliteJournalMode::Wal)
-                    .synchronous(SqliteSynchronous::Normal)
-                    .busy_timeout(Duration::from_secs(5))
-                    .foreign_keys(true)
-                    .auto_vacuum(SqliteAutoVacuum::Incremental)
-                    .pragma("cache_size", "-20000")
-                    .pragma("temp_store", "MEMORY")
-                    .pragma("mmap_size", "2147483648")
-                    .page_size(8192),
-            )
-            .await
-            .expect("Failed to connect to SQLite");
+        Arc::new(
+            Database::open(&database_url)
+                .await
+                .expect("Failed to open database"),
+        )
+    };
 
-        sqlx::migrate!("./migrations")
-            .run(&pool)
-            .await
-            .expect("Failed to run migrations");
+    // an http(s) mirror, a file:// URL or a directory, defaults to datasets.imdbws.com
+    let source = match env::var("DATASET_SOURCE") {
+        Ok(source) => DatasetSource::parse(&source)?,
+        Err(_) => DatasetSource::default(),
+    };
 
-        pool
+    // s3://bucket/key to upload a copy of the database to after syncs
+    let snapshot = match env::var("SNAPSHOT_TARGET") {
+        Ok(target) => Some(SnapshotTarget::parse(&target)?),
+        Err(_) => None,
     };
 
-    let pool_clone = pool.clone();
-    tokio::spawn(async move {
-        loop {
-            sync_data(&data_dir, &pool_clone).await.unwrap();
-            tokio::time::sleep(Duration::from_secs(14400)).await; // 4 hours
-        }
-    });
+    // fraction of rows per file that can fail to parse before the import fails
+    let max_rejected_rate = match env::var("MAX_REJECTED_RATE") {
+        Ok(rate) => rate
+            .parse::<f64>()
+            .map_err(|e| format!("Invalid MAX_REJECTED_RATE {rate:?}: {e}"))?,
+        Err(_) => DEFAULT_MAX_REJECTED_RATE,
+    };
+
+    // re-download and re-import everything, e.g. after fixing an importer
+    let force_sync = env::var("FORCE_SYNC").is_ok_and(|value| value == "1" || value == "true");
+    tokio::spawn(run_sync_scheduler(
+        data_dir.clone(),
+        db.clone(),
+        source,
+        force_sync,
+        snapshot,
+        max_rejected_rate,
+    ));
 
     let schema = Schema::build(Query, EmptyMutation, EmptySubscription)
-        .data(DataLoader::new(
-            TitleLoader::new(pool.clone()),
-            tokio::spawn,
-        ))
-        .data(pool)
+        .data(DataDir(data_dir))
         .finish();
 
-    let app = Router::new().route("/", get(graphiql).post_service(GraphQL::new(schema)));
+    let app = Router::new()
+        .route("/", get(graphiql).post(graphql))
+        .with_state(AppState { schema, db });
 
     let bind_host = env::var("HUBBLE_HOST").unwrap_or("0.0.0.0".to_string());
     let bind_port = env::var("HUBBLE_HOST").unwrap_or("8000".to_string());
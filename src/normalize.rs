@@ -0,0 +1,30 @@
+use itertools::Itertools;
+use unicode_normalization::{char::is_combining_mark, UnicodeNormalization};
+
+/// Normalize a title for comparisons that ignore case, accents and punctuation.
+/// "Amélie", "AMELIE" and "Amelie!" all normalize to "amelie".
+pub fn normalize_title(title: &str) -> String {
+    title
+        .nfkd()
+        .filter(|c| !is_combining_mark(*c))
+        .flat_map(|c| {
+            // letters that don't decompose into a base letter and a mark
+            let folded = match c {
+                'ß' => "ss",
+                'æ' | 'Æ' => "ae",
+                'œ' | 'Œ' => "oe",
+                'ø' | 'Ø' => "o",
+                'ł' | 'Ł' => "l",
+                'đ' | 'Đ' => "d",
+                'þ' | 'Þ' => "th",
+                _ => return vec![c],
+            };
+
+            folded.chars().collect()
+        })
+        .map(|c| if c.is_alphanumeric() { c } else { ' ' })
+        .collect::<String>()
+        .to_lowercase()
+        .split_whitespace()
+        .join(" ")
+}
@@ -0,0 +1,210 @@
+use async_graphql::SimpleObject;
+use chrono::Datelike;
+use regex::Regex;
+use std::sync::LazyLock;
+
+static EXTENSION: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)\.(mkv|mp4|m4v|avi|mov|wmv|webm|ts|m2ts|mpg|mpeg|srt|ass|sub|nfo)$").unwrap()
+});
+
+/// Release group tags like "[SubsPlease]" at the start of the name
+static LEADING_GROUP: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^\s*\[[^\]]*\]").unwrap());
+
+/// S02E05, s2e5, S02 E05 and multi-episode S02E05E06 (which resolves to the first episode)
+static SEASON_EPISODE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)\bS(\d{1,3}) ?E(\d{1,4})").unwrap());
+
+/// 2x05
+static SEASON_X_EPISODE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)\b(\d{1,2})x(\d{2,3})\b").unwrap());
+
+static YEAR: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"[(\[]?\b((?:19|20)\d{2})\b[)\]]?").unwrap());
+
+/// Tags that only appear after the title in release names. Longer forms come first so "WEB-DL" isn't matched as "WEB".
+static RELEASE_TAG: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)\b(2160p|1080p|1080i|720p|576p|480p|4k|uhd|web-?dl|web-?rip|web|blu-?ray|bdrip|brrip|hdtv|hdrip|dvdrip|dvd|x264|x265|h ?264|h ?265|hevc|avc|remux|proper|repack|extended|unrated|imax|multi|complete)\b").unwrap()
+});
+
+/// Tags that are also common words in titles ("Charlotte's Web", "The Complete Guide"),
+/// so they only end the title when another tag follows them
+const WEAK_TAGS: [&str; 12] = [
+    "web", "complete", "proper", "repack", "extended", "unrated", "imax", "multi", "dvd", "4k",
+    "uhd", "remux",
+];
+
+#[derive(SimpleObject, Debug, Clone, PartialEq)]
+pub struct ParsedRelease {
+    /// The series or movie name
+    pub title: String,
+    pub year: Option<i32>,
+    pub season_number: Option<i32>,
+    pub episode_number: Option<i32>,
+}
+
+/// Parse a media file or release name like "The.Expanse.S02E05.1080p.WEB.mkv" or "Amelie (2001).mkv".
+pub fn parse_release(name: &str) -> ParsedRelease {
+    let name = EXTENSION.replace(name.trim(), "");
+    let name = LEADING_GROUP.replace(&name, "");
+    // dots and underscores are used instead of spaces
+    let name = name.replace(['.', '_'], " ");
+
+    // the title ends at the first episode marker or release tag, whichever comes first
+    let mut end = name.len();
+    let mut season_number = None;
+    let mut episode_number = None;
+    let episode = SEASON_EPISODE
+        .captures(&name)
+        .or_else(|| SEASON_X_EPISODE.captures(&name));
+
+    if let Some(captures) = episode {
+        end = captures.get(0).unwrap().start();
+        season_number = captures[1].parse().ok();
+        episode_number = captures[2].parse().ok();
+    }
+
+    let tags = RELEASE_TAG.find_iter(&name).collect::<Vec<_>>();
+    let title_end_tag = tags.iter().enumerate().find(|(i, tag)| {
+        // ignore tags at the very start, a title can't be empty
+        if tag.start() == 0 {
+            return false;
+        }
+        let is_weak = WEAK_TAGS.contains(&tag.as_str().to_lowercase().as_str());
+        !is_weak
+            || tags
+                .get(i + 1)
+                .is_some_and(|next| is_separator(&name[tag.end()..next.start()]))
+    });
+    if let Some((_, tag)) = title_end_tag {
+        end = end.min(tag.start());
+    }
+
+    // the release year is the last one in brackets or right before the tags or episode, earlier ones
+    // are part of the title ("2001 A Space Odyssey 1968", "Blade Runner 2049 (2017)").
+    // Years in the future can't be release years ("Blade Runner 2049").
+    let max_year = chrono::Utc::now().year() + 1;
+    let mut year = None;
+    if let Some((start, found)) = YEAR
+        .captures_iter(&name[..end])
+        .filter_map(|captures| {
+            let found = captures.get(0).unwrap();
+            let year = captures[1].parse::<i32>().ok()?;
+            let is_bracketed = found.as_str().starts_with(['(', '[']);
+            let is_last = is_separator(&name[found.end()..end]);
+            (found.start() > 0 && year <= max_year && (is_bracketed || is_last))
+                .then_some((found.start(), year))
+        })
+        .last()
+    {
+        end = start;
+        year = Some(found);
+    }
+
+    let title = name[..end]
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .trim_matches(|c: char| c == '-' || c == '(' || c == '[' || c.is_whitespace())
+        .to_string();
+
+    ParsedRelease {
+        title,
+        year,
+        season_number,
+        episode_number,
+    }
+}
+
+fn is_separator(text: &str) -> bool {
+    text.chars()
+        .all(|c| c.is_whitespace() || matches!(c, '-' | '(' | ')' | '[' | ']'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn release(
+        title: &str,
+        year: Option<i32>,
+        season_number: Option<i32>,
+        episode_number: Option<i32>,
+    ) -> ParsedRelease {
+        ParsedRelease {
+            title: title.to_string(),
+            year,
+            season_number,
+            episode_number,
+        }
+    }
+
+    #[test]
+    fn parses_release_names() {
+        let cases = [
+            (
+                "The.Expanse.S02E05.1080p.WEB.H264-GROUP.mkv",
+                release("The Expanse", None, Some(2), Some(5)),
+            ),
+            (
+                "the_expanse_s02e05e06_720p_hdtv.mkv",
+                release("the expanse", None, Some(2), Some(5)),
+            ),
+            (
+                "Friends.1x02.The.One.With.The.Sonogram.avi",
+                release("Friends", None, Some(1), Some(2)),
+            ),
+            (
+                "[SubsPlease] Sousou no Frieren S01E05 (1080p) [ABCD1234].mkv",
+                release("Sousou no Frieren", None, Some(1), Some(5)),
+            ),
+            (
+                "Doctor.Who.2005.S01E01.Rose.720p.BluRay.mkv",
+                release("Doctor Who", Some(2005), Some(1), Some(1)),
+            ),
+            (
+                "Blade.Runner.2049.2017.1080p.BluRay.x264-SPARKS.mkv",
+                release("Blade Runner 2049", Some(2017), None, None),
+            ),
+            (
+                "Blade Runner 2049 (2017).mkv",
+                release("Blade Runner 2049", Some(2017), None, None),
+            ),
+            (
+                "Blade.Runner.2049.mkv",
+                release("Blade Runner 2049", None, None, None),
+            ),
+            (
+                "2001.A.Space.Odyssey.1968.1080p.BluRay.mkv",
+                release("2001 A Space Odyssey", Some(1968), None, None),
+            ),
+            (
+                "Amelie (2001).mkv",
+                release("Amelie", Some(2001), None, None),
+            ),
+            (
+                "Charlottes.Web.2006.1080p.WEB-DL.mkv",
+                release("Charlottes Web", Some(2006), None, None),
+            ),
+            (
+                "Charlottes Web.mkv",
+                release("Charlottes Web", None, None, None),
+            ),
+            (
+                "The.Complete.Guide.To.Everything.S01E02.720p.HDTV.x264.mkv",
+                release("The Complete Guide To Everything", None, Some(1), Some(2)),
+            ),
+            (
+                "Movie.Name.2010.EXTENDED.1080p.mkv",
+                release("Movie Name", Some(2010), None, None),
+            ),
+            (
+                "Nineteen.Eighty-Four.1984.PROPER.REPACK.mkv",
+                release("Nineteen Eighty-Four", Some(1984), None, None),
+            ),
+        ];
+
+        for (name, expected) in cases {
+            assert_eq!(parse_release(name), expected, "{name}");
+        }
+    }
+}
@@ -0,0 +1,34 @@
+use anyhow::Result;
+use sqlx::SqlitePool;
+use std::time::Instant;
+
+/// Number every series' episodes across seasons in airing order, used by `Episode.absoluteNumber`.
+/// Specials (season 0) and episodes without a season or episode number aren't numbered.
+pub async fn rebuild_absolute_numbers(pool: &SqlitePool) -> Result<()> {
+    tracing::info!("rebuilding episode absolute numbers");
+    let start = Instant::now();
+    let mut tx = pool.begin().await?;
+
+    sqlx::query!("UPDATE episodes SET absolute_number = NULL WHERE absolute_number IS NOT NULL")
+        .execute(&mut *tx)
+        .await?;
+
+    sqlx::query!(
+        "UPDATE episodes SET absolute_number = numbered.absolute_number
+        FROM (
+            SELECT id, ROW_NUMBER() OVER (PARTITION BY parent_id ORDER BY season_number, episode_number, id) AS absolute_number
+            FROM episodes
+            WHERE season_number >= 1 AND episode_number IS NOT NULL
+        ) AS numbered
+        WHERE episodes.id = numbered.id"
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+    tracing::info!(
+        "episode absolute numbers rebuild complete in {:?}",
+        start.elapsed()
+    );
+    Ok(())
+}
@@ -0,0 +1,40 @@
+use anyhow::Result;
+use sqlx::SqlitePool;
+use std::time::Instant;
+
+/// The number of votes at which a title's own rating counts as much as the mean rating of all titles.
+/// This is the same threshold IMDb uses for its top 250.
+static CHART_MIN_VOTES: i64 = 25000;
+
+/// Recompute the bayesian weighted rating for every rated title, used by `Query.chart`.
+pub async fn rebuild_charts(pool: &SqlitePool) -> Result<()> {
+    tracing::info!("rebuilding chart scores");
+    let start = Instant::now();
+    let mut tx = pool.begin().await?;
+
+    sqlx::query!("DELETE FROM chart_scores")
+        .execute(&mut *tx)
+        .await?;
+
+    // weighted rating = (v / (v + m)) * R + (m / (v + m)) * C
+    // where C is the mean rating of all rated titles
+    sqlx::query!(
+        "WITH mean AS (SELECT AVG(average_rating) AS mean FROM ratings)
+        INSERT INTO chart_scores (id, type, weighted_rating, num_votes)
+        SELECT
+            r.id,
+            t.type,
+            (r.num_votes * r.average_rating + ?1 * m.mean) / (r.num_votes + ?1),
+            r.num_votes
+        FROM ratings r
+        JOIN titles t ON t.id = r.id
+        CROSS JOIN mean m",
+        CHART_MIN_VOTES
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+    tracing::info!("chart scores rebuild complete in {:?}", start.elapsed());
+    Ok(())
+}
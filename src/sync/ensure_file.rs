@@ -1,64 +1,128 @@
-use crate::sync::file_meta::FileMeta;
-use anyhow::Result;
+use crate::sync::{
+    file_meta::{FileMeta, PartialDownload},
+    source::DatasetSource,
+};
+use anyhow::{Context, Result};
+use async_compression::tokio::bufread::GzipDecoder;
 use futures::StreamExt;
-use std::io::Write;
+use reqwest::{header::HeaderMap, RequestBuilder, StatusCode};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use tokio::io::{AsyncReadExt, AsyncWriteExt, BufReader};
 
-pub async fn ensure_file(meta: &mut FileMeta, url: &str) -> Result<bool> {
-    let client = reqwest::Client::new();
-    let mut request = client.get(url);
-    if let Some(etag) = &meta.etag {
+/// Fetch the file from the source if it has a newer version, returning whether its contents changed.
+pub async fn ensure_file(
+    meta: &mut FileMeta,
+    source: &DatasetSource,
+    file_name: &str,
+    force: bool,
+) -> Result<bool> {
+    match source {
+        DatasetSource::Http { base_url } => {
+            let request = reqwest::Client::new().get(format!("{base_url}/{file_name}"));
+            download(meta, request, force).await
+        }
+        DatasetSource::S3 { bucket, prefix } => {
+            let key = if prefix.is_empty() {
+                file_name.to_string()
+            } else {
+                format!("{prefix}/{file_name}")
+            };
+            let request = bucket.get(&reqwest::Client::new(), &key)?;
+            download(meta, request, force).await
+        }
+        DatasetSource::Local { dir } => copy_local(meta, &dir.join(file_name), force).await,
+    }
+}
+
+/// Download the file if the remote has a newer version, returning whether its contents changed.
+///
+/// ETag and Last-Modified are sent as conditional headers so unchanged files usually aren't downloaded at all.
+/// If the server sends the file anyway, its SHA-256 is compared to the one recorded in `FileMeta` so an
+/// identical file isn't imported again. `force` skips both checks.
+///
+/// Downloads go to a `.part` file next to the real one, which is resumed with a range request if a
+/// previous download was interrupted and the remote version hasn't changed since. The real file and
+/// its metadata are only replaced once the download is complete and the gzip stream checks out.
+async fn download(meta: &mut FileMeta, mut request: RequestBuilder, force: bool) -> Result<bool> {
+    let part_path = get_part_path(&meta.path);
+    if let Some(etag) = meta.etag.as_ref().filter(|_| !force) {
         request = request.header("If-None-Match", etag);
     }
 
-    if let Some(last_modified) = &meta.last_modified {
-        request = request.header("If-Modified-Since", last_modified.to_rfc2822());
+    if let Some(last_modified) = meta.last_modified.filter(|_| !force) {
+        request = request.header("If-Modified-Since", http_date(last_modified));
     }
 
-    let response = request.send().await?.error_for_status()?;
-    match response.status() {
-        reqwest::StatusCode::OK => {
-            meta.etag = response
-                .headers()
-                .get("ETag")
-                .map(|v| v.to_str().unwrap().to_string());
-
-            meta.last_modified = response.headers().get("Last-Modified").map(|v| {
-                chrono::DateTime::parse_from_rfc2822(v.to_str().unwrap())
-                    .unwrap()
-                    .with_timezone(&chrono::Utc)
-            });
-
-            if meta.path.exists() {
-                let content_length = response
-                    .headers()
-                    .get("Content-Length")
-                    .map(|v| v.to_str().unwrap().parse::<u64>().unwrap());
-
-                if let Some(content_length) = content_length {
-                    let disk_size = meta.path.metadata().unwrap().len();
-                    if disk_size == content_length {
-                        tracing::debug!(
-                            file = ?meta.path,
-                            "skipping download, file on disk matches remote"
-                        );
-                        meta.save()?;
-                        return Ok(false);
-                    }
+    let resume_from = match &meta.partial {
+        Some(partial) => {
+            let validator = partial
+                .etag
+                .clone()
+                .or_else(|| partial.last_modified.map(http_date));
+            let part_size = tokio::fs::metadata(&part_path)
+                .await
+                .map(|m| m.len())
+                .unwrap_or(0);
+            match validator {
+                // If-Range makes the server send the whole file instead if it changed since
+                Some(validator) if part_size > 0 => {
+                    request = request
+                        .header("Range", format!("bytes={part_size}-"))
+                        .header("If-Range", validator);
+                    part_size
                 }
+                _ => 0,
+            }
+        }
+        None => 0,
+    };
+
+    let response = request.send().await?;
+    // the part file is at least as long as the remote file, so asking for the same range again won't help
+    if response.status() == StatusCode::RANGE_NOT_SATISFIABLE && resume_from > 0 {
+        discard_partial(meta, &part_path).await?;
+        anyhow::bail!("Server can't resume {:?} at {resume_from} bytes", meta.path);
+    }
+
+    let response = response.error_for_status()?;
+    match response.status() {
+        StatusCode::OK | StatusCode::PARTIAL_CONTENT => {
+            let (etag, last_modified) = get_validators(response.headers());
+            let resuming = response.status() == StatusCode::PARTIAL_CONTENT;
+            if resuming && content_range_start(response.headers()) != Some(resume_from) {
+                discard_partial(meta, &part_path).await?;
+                anyhow::bail!("Server resumed {:?} at the wrong offset", meta.path);
             }
 
-            let mut file = std::fs::File::create(&meta.path)?;
+            let mut file = if resuming {
+                tracing::info!(file = ?meta.path, "resuming download at {resume_from} bytes");
+                tokio::fs::OpenOptions::new()
+                    .append(true)
+                    .open(&part_path)
+                    .await?
+            } else {
+                meta.partial = Some(PartialDownload {
+                    etag: etag.clone(),
+                    last_modified,
+                });
+                meta.save()?;
+                tokio::fs::File::create(&part_path).await?
+            };
+
             let mut stream = response.bytes_stream();
             while let Some(chunk) = stream.next().await {
-                file.write_all(&chunk?)?;
+                file.write_all(&chunk?).await?;
             }
+            file.sync_all().await?;
+            drop(file);
 
-            meta.imported_at = None;
-            meta.save()?;
-            tracing::info!(file = ?meta.path, "downloaded file");
-            Ok(true)
+            finish(meta, &part_path, etag, last_modified, force).await
         }
-        reqwest::StatusCode::NOT_MODIFIED => {
+        StatusCode::NOT_MODIFIED => {
+            if meta.partial.is_some() {
+                discard_partial(meta, &part_path).await?;
+            }
             tracing::info!(file = ?meta.path, "skipping download, remote confirms our version is up to date");
             Ok(false)
         }
@@ -69,3 +133,267 @@ pub async fn ensure_file(meta: &mut FileMeta, url: &str) -> Result<bool> {
         )),
     }
 }
+
+fn get_part_path(path: &Path) -> PathBuf {
+    let mut part_path = path.as_os_str().to_owned();
+    part_path.push(".part");
+    PathBuf::from(part_path)
+}
+
+/// Copy a file from a local directory, using its modification time like Last-Modified.
+async fn copy_local(meta: &mut FileMeta, path: &Path, force: bool) -> Result<bool> {
+    let modified: chrono::DateTime<chrono::Utc> = tokio::fs::metadata(path)
+        .await
+        .with_context(|| format!("Failed to read {path:?}"))?
+        .modified()?
+        .into();
+
+    if !force && meta.path.exists() && meta.last_modified == Some(modified) {
+        tracing::info!(file = ?meta.path, "skipping copy, source file is unchanged");
+        return Ok(false);
+    }
+
+    // copy to the part file first so an interrupted copy never replaces the real one
+    let part_path = get_part_path(&meta.path);
+    tokio::fs::copy(path, &part_path).await?;
+    finish(meta, &part_path, None, Some(modified), force).await
+}
+
+/// Check a complete `.part` file and move it into place if it differs from the file we have.
+async fn finish(
+    meta: &mut FileMeta,
+    part_path: &Path,
+    etag: Option<String>,
+    last_modified: Option<chrono::DateTime<chrono::Utc>>,
+    force: bool,
+) -> Result<bool> {
+    if let Err(error) = check_gzip(part_path).await {
+        // the partial download is useless if it doesn't decompress, so start over next time
+        discard_partial(meta, part_path).await?;
+        return Err(error);
+    }
+
+    let sha256 = hash_file(part_path).await?;
+    let changed = force || !meta.path.exists() || meta.sha256.as_ref() != Some(&sha256);
+    if changed {
+        tokio::fs::rename(part_path, &meta.path).await?;
+        meta.imported_at = None;
+    } else {
+        tokio::fs::remove_file(part_path).await?;
+    }
+
+    meta.etag = etag;
+    meta.last_modified = last_modified;
+    meta.sha256 = Some(sha256);
+    meta.downloaded_at = Some(chrono::Utc::now());
+    meta.partial = None;
+    meta.save()?;
+    if changed {
+        tracing::info!(file = ?meta.path, "updated file");
+    } else {
+        tracing::info!(file = ?meta.path, "new file is identical to ours, keeping it");
+    }
+
+    Ok(changed)
+}
+
+async fn discard_partial(meta: &mut FileMeta, part_path: &Path) -> Result<()> {
+    if part_path.exists() {
+        tokio::fs::remove_file(part_path).await?;
+    }
+    meta.partial = None;
+    meta.save()
+}
+
+/// Format a date as an HTTP-date, `to_rfc2822` uses `+0000` which strict servers reject
+fn http_date(date: chrono::DateTime<chrono::Utc>) -> String {
+    date.format("%a, %d %b %Y %H:%M:%S GMT").to_string()
+}
+
+fn get_validators(headers: &HeaderMap) -> (Option<String>, Option<chrono::DateTime<chrono::Utc>>) {
+    let etag = headers
+        .get("ETag")
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.to_string());
+
+    let last_modified = headers
+        .get("Last-Modified")
+        .and_then(|v| chrono::DateTime::parse_from_rfc2822(v.to_str().ok()?).ok())
+        .map(|date| date.with_timezone(&chrono::Utc));
+
+    (etag, last_modified)
+}
+
+/// The first byte of a `Content-Range: bytes <start>-<end>/<size>` response
+fn content_range_start(headers: &HeaderMap) -> Option<u64> {
+    let range = headers.get("Content-Range")?.to_str().ok()?;
+    let range = range.strip_prefix("bytes ")?;
+    range.split('-').next()?.parse().ok()
+}
+
+async fn hash_file(path: &Path) -> Result<String> {
+    let mut file = tokio::fs::File::open(path).await?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0; 1 << 16];
+    loop {
+        let read = file.read(&mut buffer).await?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// Decompress the whole file, which fails if it is truncated or its checksum doesn't match.
+async fn check_gzip(path: &Path) -> Result<()> {
+    let file = tokio::fs::File::open(path).await?;
+    let mut decoder = GzipDecoder::new(BufReader::new(file));
+    tokio::io::copy(&mut decoder, &mut tokio::io::sink())
+        .await
+        .with_context(|| format!("{path:?} is not a complete gzip file"))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_compression::tokio::bufread::GzipEncoder;
+    use wiremock::{
+        matchers::{header, header_exists, method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    const FILE_NAME: &str = "title.ratings.tsv.gz";
+
+    async fn gzip(text: &str) -> Vec<u8> {
+        let mut compressed = Vec::new();
+        GzipEncoder::new(text.as_bytes())
+            .read_to_end(&mut compressed)
+            .await
+            .unwrap();
+        compressed
+    }
+
+    /// A file meta whose part file holds `part` from an interrupted download of version `etag`
+    fn interrupted(dir: &Path, etag: &str, part: &[u8]) -> FileMeta {
+        let mut meta = FileMeta::new(&dir.join(FILE_NAME)).unwrap();
+        std::fs::write(get_part_path(&meta.path), part).unwrap();
+        meta.partial = Some(PartialDownload {
+            etag: Some(etag.to_string()),
+            last_modified: None,
+        });
+        meta.save().unwrap();
+        meta
+    }
+
+    fn source(server: &MockServer) -> DatasetSource {
+        DatasetSource::Http {
+            base_url: server.uri(),
+        }
+    }
+
+    #[tokio::test]
+    async fn resumes_unchanged_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = gzip("tconst\taverageRating\tnumVotes\ntt0000001\t5.7\t2000\n").await;
+        let split = file.len() / 2;
+        let mut meta = interrupted(dir.path(), "\"v1\"", &file[..split]);
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path(format!("/{FILE_NAME}")))
+            .and(header("Range", format!("bytes={split}-").as_str()))
+            .and(header("If-Range", "\"v1\""))
+            .respond_with(
+                ResponseTemplate::new(206)
+                    .insert_header("ETag", "\"v1\"")
+                    .insert_header(
+                        "Content-Range",
+                        format!("bytes {split}-{}/{}", file.len() - 1, file.len()).as_str(),
+                    )
+                    .set_body_bytes(&file[split..]),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let changed = ensure_file(&mut meta, &source(&server), FILE_NAME, false)
+            .await
+            .unwrap();
+        assert!(changed);
+        assert_eq!(std::fs::read(&meta.path).unwrap(), file);
+        assert!(!get_part_path(&meta.path).exists());
+        assert!(meta.partial.is_none());
+        assert_eq!(meta.etag.as_deref(), Some("\"v1\""));
+    }
+
+    #[tokio::test]
+    async fn restarts_changed_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let old = gzip("tconst\taverageRating\tnumVotes\ntt0000001\t5.7\t2000\n").await;
+        let new = gzip("tconst\taverageRating\tnumVotes\ntt0000001\t5.8\t2100\n").await;
+        let mut meta = interrupted(dir.path(), "\"v1\"", &old[..old.len() / 2]);
+
+        // If-Range doesn't match the current version, so the server sends all of it
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path(format!("/{FILE_NAME}")))
+            .and(header_exists("If-Range"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("ETag", "\"v2\"")
+                    .set_body_bytes(new.clone()),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let changed = ensure_file(&mut meta, &source(&server), FILE_NAME, false)
+            .await
+            .unwrap();
+        assert!(changed);
+        assert_eq!(std::fs::read(&meta.path).unwrap(), new);
+        assert!(meta.partial.is_none());
+        assert_eq!(meta.etag.as_deref(), Some("\"v2\""));
+    }
+
+    #[tokio::test]
+    async fn discards_part_file_the_server_cant_resume() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = gzip("tconst\taverageRating\tnumVotes\ntt0000001\t5.7\t2000\n").await;
+        let mut meta = interrupted(dir.path(), "\"v1\"", &file);
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path(format!("/{FILE_NAME}")))
+            .and(header_exists("Range"))
+            .respond_with(ResponseTemplate::new(416))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path(format!("/{FILE_NAME}")))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("ETag", "\"v1\"")
+                    .set_body_bytes(file.clone()),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let result = ensure_file(&mut meta, &source(&server), FILE_NAME, false).await;
+        assert!(result.is_err());
+        assert!(!get_part_path(&meta.path).exists());
+        assert!(meta.partial.is_none());
+
+        // the retry downloads the whole file instead of asking for the same range
+        let changed = ensure_file(&mut meta, &source(&server), FILE_NAME, false)
+            .await
+            .unwrap();
+        assert!(changed);
+        assert_eq!(std::fs::read(&meta.path).unwrap(), file);
+    }
+}
@@ -1,4 +1,4 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
@@ -10,24 +10,53 @@ pub struct FileMeta {
     pub etag: Option<String>,
     pub last_modified: Option<chrono::DateTime<chrono::Utc>>,
     pub imported_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// Hex SHA-256 of the downloaded file, to tell if a re-download actually changed anything
+    #[serde(default)]
+    pub sha256: Option<String>,
+    /// The error from the last failed sync, cleared once the file syncs again
+    #[serde(default)]
+    pub last_error: Option<String>,
+    #[serde(default)]
+    pub failed_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// `Importer::get_version` of the importer that last imported the file
+    #[serde(default)]
+    pub import_version: u32,
+    /// Rows that failed to parse in the last import, see `Quarantine`
+    #[serde(default)]
+    pub rejected_rows: Option<u64>,
+    /// Set while a download is in progress, so it can be resumed if the remote version is unchanged
+    #[serde(default)]
+    pub partial: Option<PartialDownload>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PartialDownload {
+    pub etag: Option<String>,
+    pub last_modified: Option<chrono::DateTime<chrono::Utc>>,
 }
 
 impl FileMeta {
     /// file_name is the name of the .tsv.gz file
-    pub fn new(file_name: &PathBuf) -> Result<Self> {
+    pub fn new(file_name: &Path) -> Result<Self> {
         let meta_path = file_name.with_extension("json");
         let meta = match std::fs::read_to_string(&meta_path) {
             Ok(meta) => {
                 let data: FileMeta = serde_json::from_str(&meta)?;
-                assert_eq!(data.path, file_name.clone());
+                assert_eq!(data.path, file_name);
                 data
             }
             Err(_) => FileMeta {
-                path: file_name.clone(),
+                path: file_name.to_path_buf(),
                 downloaded_at: None,
                 etag: None,
                 last_modified: None,
                 imported_at: None,
+                sha256: None,
+                last_error: None,
+                failed_at: None,
+                import_version: 0,
+                rejected_rows: None,
+                partial: None,
             },
         };
 
@@ -37,7 +66,10 @@ impl FileMeta {
     pub fn save(&self) -> Result<()> {
         let meta_path = self.path.with_extension("json");
         let meta = serde_json::to_string_pretty(self)?;
-        std::fs::write(&meta_path, meta)?;
+        // write and rename so a crash can't leave half a file behind
+        let tmp_path = meta_path.with_extension("json.tmp");
+        std::fs::write(&tmp_path, meta)?;
+        std::fs::rename(&tmp_path, &meta_path)?;
         Ok(())
     }
 }
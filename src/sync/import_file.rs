@@ -1,22 +1,26 @@
-use super::importers::Importer;
-use crate::sync::file_meta::FileMeta;
+use super::{
+    importers::Importer,
+    quarantine::{get_quarantine_path, Quarantine},
+};
 use anyhow::Result;
 use async_compression::tokio::bufread::GzipDecoder;
-use csv_async::AsyncReaderBuilder;
+use csv_async::{AsyncReaderBuilder, StringRecord};
 use futures::StreamExt;
-use itertools::Itertools;
 use sqlx::SqlitePool;
-use std::time::Instant;
+use std::{path::Path, time::Instant};
 
 static MAX_BIND_VALUES: usize = 32766;
 static MAX_BATCH_SIZE: usize = 10000;
 
+/// Import a dataset, returning how many rows were rejected. Fails if more than `max_rejected_rate`
+/// of the rows were rejected, since that's more likely a format change than a few bad rows.
 pub async fn import_file(
     pool: &SqlitePool,
-    importer: &Box<dyn Importer>,
-    meta: &mut FileMeta,
-) -> Result<()> {
-    let file = tokio::fs::File::open(&meta.path).await?;
+    importer: &dyn Importer,
+    path: &Path,
+    max_rejected_rate: f64,
+) -> Result<u64> {
+    let file = tokio::fs::File::open(path).await?;
     let decompressor = GzipDecoder::new(tokio::io::BufReader::new(file));
     let mut reader = AsyncReaderBuilder::new()
         .has_headers(true)
@@ -25,6 +29,7 @@ pub async fn import_file(
         .create_reader(decompressor);
 
     let headers = reader.headers().await?.clone();
+    check_headers(importer, &headers)?;
     let batch_size = {
         let bind_count = importer.get_bind_count();
         (MAX_BIND_VALUES / bind_count).min(MAX_BATCH_SIZE)
@@ -33,14 +38,27 @@ pub async fn import_file(
     let name = importer.get_name();
     tracing::info!(file = name, "importing using batch size of {batch_size}");
 
+    // rows keep the ID of the import that last wrote them, see Importer::remove_stale
+    let import_id = chrono::Utc::now().timestamp_millis();
+    let mut quarantine = Quarantine::default();
     let mut stream = reader.records().chunks(batch_size);
     let mut done = 0;
     let mut last_log = Instant::now();
     let start = Instant::now();
     while let Some(batch) = stream.next().await {
         done += batch.len();
-        let rows = batch.into_iter().map(|row| row.unwrap()).collect_vec();
-        importer.write_batch(pool, &headers, rows).await?;
+        let mut rows = Vec::with_capacity(batch.len());
+        for row in batch {
+            match row {
+                Ok(row) => rows.push(row),
+                // a broken download, not a bad row
+                Err(error) if error.is_io_error() => return Err(error.into()),
+                Err(error) => quarantine.reject_error(&error),
+            }
+        }
+        importer
+            .write_batch(pool, &headers, rows, import_id, &mut quarantine)
+            .await?;
         if last_log.elapsed().as_secs() > 5 {
             let per_sec = done as f64 / start.elapsed().as_secs_f64();
             tracing::info!(
@@ -52,9 +70,140 @@ pub async fn import_file(
         }
     }
 
-    meta.imported_at = Some(chrono::Utc::now());
-    meta.save()?;
+    // an empty file is more likely a broken download than IMDb dropping every title
+    if done == 0 {
+        anyhow::bail!("{name} has no rows, not removing stale rows");
+    }
+
+    for warning in quarantine.warnings() {
+        tracing::warn!(file = name, "{warning}");
+    }
+
+    quarantine.save(path)?;
+    let rejected = quarantine.count();
+    if rejected > 0 {
+        tracing::warn!(
+            file = name,
+            "rejected {rejected} of {done} rows, samples are in {:?}",
+            get_quarantine_path(path)
+        );
+    }
+    if rejected as f64 > done as f64 * max_rejected_rate {
+        anyhow::bail!(
+            "rejected {rejected} of {done} rows, more than the maximum rate of {max_rejected_rate}"
+        );
+    }
+
+    // a rejected row keeps the ID of an older import, so removing stale rows would delete it as if it were gone
+    if rejected > 0 {
+        tracing::warn!(
+            file = name,
+            "not removing stale rows, since rejected rows may still be in the dataset"
+        );
+    } else {
+        let removed = importer.remove_stale(pool, import_id).await?;
+        tracing::info!(
+            file = name,
+            "removed {removed} rows that are no longer in the dataset"
+        );
+    }
 
     tracing::info!(file = name, "imported in {:?}", start.elapsed());
+    Ok(rejected)
+}
+
+/// Fail on missing columns, which would otherwise reject every row, and warn about new ones,
+/// which are ignored.
+fn check_headers(importer: &dyn Importer, headers: &StringRecord) -> Result<()> {
+    let columns = importer.get_columns();
+    let missing = columns
+        .iter()
+        .filter(|column| !headers.iter().any(|header| header == **column))
+        .copied()
+        .collect::<Vec<_>>();
+    if !missing.is_empty() {
+        anyhow::bail!(
+            "{} is missing the columns {}, found {}",
+            importer.get_name(),
+            missing.join(", "),
+            headers.iter().collect::<Vec<_>>().join(", ")
+        );
+    }
+
+    let extra = headers
+        .iter()
+        .filter(|header| !columns.contains(header))
+        .collect::<Vec<_>>();
+    if !extra.is_empty() {
+        tracing::warn!(
+            file = importer.get_name(),
+            "ignoring unknown columns {}",
+            extra.join(", ")
+        );
+    }
+
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sync::importers::get_importers;
+    use async_compression::tokio::write::GzipEncoder;
+    use sqlx::sqlite::SqlitePoolOptions;
+    use tokio::io::AsyncWriteExt;
+
+    async fn write_gz(path: &Path, contents: &str) {
+        let mut encoder = GzipEncoder::new(tokio::fs::File::create(path).await.unwrap());
+        encoder.write_all(contents.as_bytes()).await.unwrap();
+        encoder.shutdown().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn keeps_titles_of_rejected_rows() {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        sqlx::migrate!("./migrations").run(&pool).await.unwrap();
+        let importer = get_importers().into_iter().next().unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("title.basics.tsv.gz");
+        let header = "tconst\ttitleType\tprimaryTitle\toriginalTitle\tisAdult\tstartYear\tendYear\truntimeMinutes\tgenres\n";
+
+        write_gz(
+            &path,
+            &format!("{header}tt1\tmovie\tOne\tOne\t0\t1990\t\\N\t90\tDrama\ntt2\tmovie\tTwo\tTwo\t0\t1991\t\\N\t90\tDrama\n"),
+        )
+        .await;
+        import_file(&pool, importer.as_ref(), &path, 1.0)
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO ratings (id, average_rating, num_votes) VALUES (1, 7.5, 100)")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        // tt1 is still in the dataset, but its row no longer parses
+        write_gz(
+            &path,
+            &format!("{header}tt1\tmovie\tOne\tOne\t0\tsoon\t\\N\t90\tDrama\ntt2\tmovie\tTwo\tTwo\t0\t1991\t\\N\t90\tDrama\n"),
+        )
+        .await;
+        let rejected = import_file(&pool, importer.as_ref(), &path, 1.0)
+            .await
+            .unwrap();
+        assert_eq!(rejected, 1);
+
+        let titles: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM titles")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        let ratings: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM ratings WHERE id = 1")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!((titles, ratings), (2, 1));
+    }
+}
@@ -1,6 +1,8 @@
 use crate::id::Id;
-use crate::sync::importers::Importer;
+use crate::normalize::normalize_title;
+use crate::sync::importers::{deserialize_rows, Importer};
 use crate::sync::nullable::nullable;
+use crate::sync::quarantine::Quarantine;
 use anyhow::Result;
 use csv_async::StringRecord;
 use serde::Deserialize;
@@ -37,12 +39,26 @@ impl Importer for AkasImporter {
         "title.akas.tsv.gz"
     }
 
-    fn get_url(&self) -> &str {
-        "https://datasets.imdbws.com/title.akas.tsv.gz"
+    fn get_version(&self) -> u32 {
+        // 1: normalized titles are stored for matching
+        1
+    }
+
+    fn get_columns(&self) -> &[&str] {
+        &[
+            "titleId",
+            "ordering",
+            "title",
+            "region",
+            "language",
+            "types",
+            "attributes",
+            "isOriginalTitle",
+        ]
     }
 
     fn get_bind_count(&self) -> usize {
-        8
+        10
     }
 
     async fn write_batch(
@@ -50,17 +66,21 @@ impl Importer for AkasImporter {
         pool: &SqlitePool,
         headers: &StringRecord,
         rows: Vec<StringRecord>,
+        import_id: i64,
+        quarantine: &mut Quarantine,
     ) -> Result<()> {
         let mut qb = QueryBuilder::new(
-            "INSERT OR REPLACE INTO akas (id, ordering, title, region, language, types, attributes, is_original_title) "
+            "INSERT OR REPLACE INTO akas (id, ordering, title, region, language, types, attributes, is_original_title, normalized_title, import_id) "
         );
 
-        let rows: Vec<AkasRow> = rows
-            .into_iter()
-            .filter_map(|row| row.deserialize(Some(headers)).ok())
-            .collect();
+        let rows: Vec<AkasRow> = deserialize_rows(headers, rows, quarantine);
+        // every row of the batch was rejected, and push_values needs at least one row
+        if rows.is_empty() {
+            return Ok(());
+        }
 
         qb.push_values(rows, |mut qb, row| {
+            let normalized_title = normalize_title(&row.title);
             qb.push_bind(row.title_id.get())
                 .push_bind(row.ordering)
                 .push_bind(row.title)
@@ -68,11 +88,46 @@ impl Importer for AkasImporter {
                 .push_bind(row.language)
                 .push_bind(row.types)
                 .push_bind(row.attributes)
-                .push_bind(row.is_original_title);
+                .push_bind(row.is_original_title)
+                .push_bind(normalized_title)
+                .push_bind(import_id);
         });
 
         let query = qb.build();
         query.execute(pool).await?;
         Ok(())
     }
+
+    async fn remove_stale(&self, pool: &SqlitePool, import_id: i64) -> Result<u64> {
+        let result = sqlx::query!("DELETE FROM akas WHERE import_id != ?", import_id)
+            .execute(pool)
+            .await?;
+        Ok(result.rows_affected())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sync::importers::parse_sample;
+
+    #[test]
+    fn columns_match_row() {
+        let row: AkasRow = parse_sample(
+            &AkasImporter,
+            &[
+                "tt0000001",
+                "1",
+                "Carmencita",
+                "DE",
+                "\\N",
+                "imdbDisplay",
+                "\\N",
+                "0",
+            ],
+        );
+        assert_eq!(row.title, "Carmencita");
+        assert_eq!(row.region.as_deref(), Some("DE"));
+        assert!(!row.is_original_title);
+    }
 }
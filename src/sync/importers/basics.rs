@@ -1,7 +1,10 @@
+use crate::genre::Genre;
 use crate::id::Id;
 use crate::kind::TitleKind;
-use crate::sync::importers::Importer;
+use crate::normalize::normalize_title;
+use crate::sync::importers::{deserialize_rows, Importer};
 use crate::sync::nullable::nullable;
+use crate::sync::quarantine::Quarantine;
 use anyhow::Result;
 use csv_async::StringRecord;
 use serde::Deserialize;
@@ -14,7 +17,7 @@ use sqlx::{QueryBuilder, SqlitePool};
 struct BasicsRow {
     tconst: Id,
     #[serde(rename = "titleType")]
-    title_type: TitleKind,
+    title_type: String,
     #[serde(rename = "primaryTitle")]
     primary_title: String,
     #[serde(rename = "originalTitle")]
@@ -43,12 +46,27 @@ impl Importer for BasicsImporter {
         "title.basics.tsv.gz"
     }
 
-    fn get_url(&self) -> &str {
-        "https://datasets.imdbws.com/title.basics.tsv.gz"
+    fn get_version(&self) -> u32 {
+        // 1: normalized primary and original titles are stored for matching
+        1
+    }
+
+    fn get_columns(&self) -> &[&str] {
+        &[
+            "tconst",
+            "titleType",
+            "primaryTitle",
+            "originalTitle",
+            "isAdult",
+            "startYear",
+            "endYear",
+            "runtimeMinutes",
+            "genres",
+        ]
     }
 
     fn get_bind_count(&self) -> usize {
-        9
+        13
     }
 
     async fn write_batch(
@@ -56,13 +74,39 @@ impl Importer for BasicsImporter {
         pool: &SqlitePool,
         headers: &StringRecord,
         rows: Vec<StringRecord>,
+        import_id: i64,
+        quarantine: &mut Quarantine,
     ) -> Result<()> {
-        let mut qb = QueryBuilder::new("INSERT OR REPLACE INTO titles (id, type, primary_title, original_title, is_adult, start_year, end_year, runtime_minutes, genres) ");
+        let mut qb = QueryBuilder::new("INSERT OR REPLACE INTO titles (id, type, type_name, primary_title, original_title, is_adult, start_year, end_year, runtime_minutes, genres, normalized_primary_title, normalized_original_title, import_id) ");
+
+        let rows: Vec<BasicsRow> = deserialize_rows(headers, rows, quarantine);
+        // every row of the batch was rejected, and push_values needs at least one row
+        if rows.is_empty() {
+            return Ok(());
+        }
 
-        let rows: Vec<BasicsRow> = rows
-            .into_iter()
-            .filter_map(|row| row.deserialize(Some(headers)).ok())
-            .collect();
+        // titles of types this version doesn't know are kept as OTHER with their type name
+        for row in &rows {
+            if TitleKind::from_imdb(&row.title_type).is_none() {
+                quarantine.warn(format!(
+                    "unknown title type {:?}, importing its titles with kind OTHER",
+                    row.title_type
+                ));
+            }
+        }
+
+        let ids = rows.iter().map(|row| row.tconst.get()).collect::<Vec<_>>();
+        let mut genres = Vec::new();
+        for row in &rows {
+            for name in row.genres.iter().flat_map(|genres| genres.split(',')) {
+                match Genre::from_imdb(name) {
+                    Some(genre) => genres.push((row.tconst.get(), genre)),
+                    None => quarantine.warn(format!(
+                        "unknown genre {name:?}, importing its titles without it"
+                    )),
+                }
+            }
+        }
 
         qb.push_values(rows, |mut qb, row| {
             let id = row.tconst.get();
@@ -72,20 +116,119 @@ impl Importer for BasicsImporter {
                 Some(row.original_title)
             };
 
-            let kind = row.title_type as i32;
+            let (kind, type_name) = match TitleKind::from_imdb(&row.title_type) {
+                Some(kind) => (kind, None),
+                None => (TitleKind::Other, Some(row.title_type)),
+            };
+            let normalized_primary_title = normalize_title(&row.primary_title);
+            let normalized_original_title = original_title.as_deref().map(normalize_title);
             qb.push_bind(id)
-                .push_bind(kind)
+                .push_bind(kind as i64)
+                .push_bind(type_name)
                 .push_bind(row.primary_title)
                 .push_bind(original_title)
                 .push_bind(row.is_adult)
                 .push_bind(row.start_year)
                 .push_bind(row.end_year)
                 .push_bind(row.runtime_minutes)
-                .push_bind(row.genres);
+                .push_bind(row.genres)
+                .push_bind(normalized_primary_title)
+                .push_bind(normalized_original_title)
+                .push_bind(import_id);
         });
 
-        let query = qb.build();
-        query.execute(pool).await?;
+        let mut tx = pool.begin().await?;
+        qb.build().execute(&mut *tx).await?;
+
+        // genres can change between dataset versions, so replace them instead of merging
+        let mut qb = QueryBuilder::new("DELETE FROM title_genres WHERE title_id IN (");
+        let mut separated = qb.separated(", ");
+        for id in ids {
+            separated.push_bind(id);
+        }
+        qb.push(")");
+        qb.build().execute(&mut *tx).await?;
+
+        if !genres.is_empty() {
+            let mut qb = QueryBuilder::new("INSERT INTO title_genres (title_id, genre_id) ");
+            qb.push_values(genres, |mut qb, (id, genre)| {
+                qb.push_bind(id).push_bind(genre as i64);
+            });
+            qb.build().execute(&mut *tx).await?;
+        }
+
+        tx.commit().await?;
         Ok(())
     }
+
+    /// Removing a title also removes everything that refers to it, since foreign keys are enforced.
+    async fn remove_stale(&self, pool: &SqlitePool, import_id: i64) -> Result<u64> {
+        let mut tx = pool.begin().await?;
+        sqlx::query!(
+            "CREATE TEMP TABLE stale_titles AS SELECT id FROM titles WHERE import_id != ?",
+            import_id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        let cascade = [
+            ("search_index", "DELETE FROM search_index WHERE title_id IN (SELECT id FROM stale_titles)"),
+            ("chart_scores", "DELETE FROM chart_scores WHERE id IN (SELECT id FROM stale_titles)"),
+            ("title_genres", "DELETE FROM title_genres WHERE title_id IN (SELECT id FROM stale_titles)"),
+            ("ratings", "DELETE FROM ratings WHERE id IN (SELECT id FROM stale_titles)"),
+            ("akas", "DELETE FROM akas WHERE id IN (SELECT id FROM stale_titles)"),
+            ("episodes", "DELETE FROM episodes WHERE id IN (SELECT id FROM stale_titles) OR parent_id IN (SELECT id FROM stale_titles)"),
+        ];
+
+        // the temp table doesn't exist at compile time, so these can't use the checked macros
+        let stale: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM stale_titles")
+            .fetch_one(&mut *tx)
+            .await?;
+        if stale > 0 {
+            for (table, statement) in cascade {
+                let result = sqlx::query(statement).execute(&mut *tx).await?;
+                tracing::info!(
+                    "removed {} rows from {table} for titles that are no longer in the dataset",
+                    result.rows_affected()
+                );
+            }
+
+            sqlx::query("DELETE FROM titles WHERE id IN (SELECT id FROM stale_titles)")
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        sqlx::query("DROP TABLE stale_titles")
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(stale as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sync::importers::parse_sample;
+
+    #[test]
+    fn columns_match_row() {
+        let row: BasicsRow = parse_sample(
+            &BasicsImporter,
+            &[
+                "tt0000001",
+                "short",
+                "Carmencita",
+                "Carmencita",
+                "0",
+                "1894",
+                "\\N",
+                "1",
+                "Documentary,Short",
+            ],
+        );
+        assert_eq!(row.title_type, "short");
+        assert_eq!(row.start_year, Some(1894));
+        assert_eq!(row.genres.as_deref(), Some("Documentary,Short"));
+    }
 }
@@ -1,5 +1,11 @@
 use crate::sync::nullable::nullable;
-use crate::{id::Id, sync::importers::Importer};
+use crate::{
+    id::Id,
+    sync::{
+        importers::{deserialize_rows, Importer},
+        quarantine::Quarantine,
+    },
+};
 use anyhow::Result;
 use csv_async::StringRecord;
 use serde::Deserialize;
@@ -26,12 +32,17 @@ impl Importer for EpisodesImporter {
         "title.episode.tsv.gz"
     }
 
-    fn get_url(&self) -> &str {
-        "https://datasets.imdbws.com/title.episode.tsv.gz"
+    fn get_version(&self) -> u32 {
+        // 1: episodes without a season or episode number are kept instead of skipped
+        1
+    }
+
+    fn get_columns(&self) -> &[&str] {
+        &["tconst", "parentTconst", "seasonNumber", "episodeNumber"]
     }
 
     fn get_bind_count(&self) -> usize {
-        4
+        5
     }
 
     async fn write_batch(
@@ -39,26 +50,50 @@ impl Importer for EpisodesImporter {
         pool: &SqlitePool,
         headers: &StringRecord,
         rows: Vec<StringRecord>,
+        import_id: i64,
+        quarantine: &mut Quarantine,
     ) -> Result<()> {
         let mut qb = QueryBuilder::new(
-            "INSERT OR REPLACE INTO episodes (id, parent_id, season_number, episode_number) ",
+            "INSERT OR REPLACE INTO episodes (id, parent_id, season_number, episode_number, import_id) ",
         );
-        let rows: Vec<EpisodesRow> = rows
-            .into_iter()
-            .filter_map(|row| row.deserialize(Some(headers)).ok())
-            // filter out rows with null season or episode numbers
-            .filter(|row: &EpisodesRow| row.season_number.is_some() && row.episode_number.is_some())
-            .collect();
+        let rows: Vec<EpisodesRow> = deserialize_rows(headers, rows, quarantine);
+        // every row of the batch was rejected, and push_values needs at least one row
+        if rows.is_empty() {
+            return Ok(());
+        }
 
         qb.push_values(rows, |mut qb, row| {
             qb.push_bind(row.tconst.get())
                 .push_bind(row.parent_tconst.get())
                 .push_bind(row.season_number)
-                .push_bind(row.episode_number);
+                .push_bind(row.episode_number)
+                .push_bind(import_id);
         });
 
         let query = qb.build();
         query.execute(pool).await?;
         Ok(())
     }
+
+    async fn remove_stale(&self, pool: &SqlitePool, import_id: i64) -> Result<u64> {
+        let result = sqlx::query!("DELETE FROM episodes WHERE import_id != ?", import_id)
+            .execute(pool)
+            .await?;
+        Ok(result.rows_affected())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sync::importers::parse_sample;
+
+    #[test]
+    fn columns_match_row() {
+        let row: EpisodesRow =
+            parse_sample(&EpisodesImporter, &["tt0041951", "tt0041038", "1", "9"]);
+        assert_eq!(row.parent_tconst.get(), 41038);
+        assert_eq!(row.season_number, Some(1));
+        assert_eq!(row.episode_number, Some(9));
+    }
 }
@@ -1,4 +1,6 @@
+use super::quarantine::Quarantine;
 use csv_async::StringRecord;
+use serde::de::DeserializeOwned;
 use sqlx::SqlitePool;
 
 mod akas;
@@ -9,15 +11,27 @@ mod ratings;
 #[async_trait::async_trait]
 pub trait Importer: Send + Sync {
     fn get_name(&self) -> &str;
-    fn get_url(&self) -> &str;
+    /// Bumped whenever the importer changes what it writes, so files imported by an older version
+    /// are imported again even if the dataset didn't change
+    fn get_version(&self) -> u32;
+    /// Columns the rows are deserialized from, all of which must be in the file's header.
+    /// Each importer's `columns_match_row` test checks them against the row's field names.
+    fn get_columns(&self) -> &[&str];
     fn get_bind_count(&self) -> usize;
 
+    /// Write rows, tagging them with `import_id`. Rows that don't parse go to `quarantine`.
     async fn write_batch(
         &self,
         pool: &SqlitePool,
         headers: &StringRecord,
         rows: Vec<StringRecord>,
+        import_id: i64,
+        quarantine: &mut Quarantine,
     ) -> anyhow::Result<()>;
+
+    /// Delete rows that were not written by the import with `import_id`, because they are no longer in the
+    /// dataset, returning how many were deleted.
+    async fn remove_stale(&self, pool: &SqlitePool, import_id: i64) -> anyhow::Result<u64>;
 }
 
 pub fn get_importers() -> Vec<Box<dyn Importer>> {
@@ -28,3 +42,65 @@ pub fn get_importers() -> Vec<Box<dyn Importer>> {
         Box::new(ratings::RatingsImporter),
     ]
 }
+
+/// Deserialize rows, quarantining the ones that fail instead of dropping them silently.
+fn deserialize_rows<T: DeserializeOwned>(
+    headers: &StringRecord,
+    rows: Vec<StringRecord>,
+    quarantine: &mut Quarantine,
+) -> Vec<T> {
+    rows.into_iter()
+        .filter_map(|row| match row.deserialize(Some(headers)) {
+            Ok(parsed) => Some(parsed),
+            Err(error) => {
+                quarantine.reject_row(&row, error);
+                None
+            }
+        })
+        .collect()
+}
+
+/// Deserialize a row with a header built from `get_columns()`, which fails if the list is missing a
+/// column the row needs or names it differently than its serde rename.
+#[cfg(test)]
+fn parse_sample<T: DeserializeOwned>(importer: &dyn Importer, values: &[&str]) -> T {
+    let columns = importer.get_columns();
+    assert_eq!(columns.len(), values.len(), "one value per column");
+    let headers = StringRecord::from(columns.to_vec());
+    StringRecord::from(values.to_vec())
+        .deserialize(Some(&headers))
+        .unwrap_or_else(|error| panic!("{}: {error}", importer.get_name()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    async fn pool() -> SqlitePool {
+        // every connection to :memory: is a separate database
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        sqlx::migrate!("./migrations").run(&pool).await.unwrap();
+        pool
+    }
+
+    #[tokio::test]
+    async fn skips_batches_of_rejected_rows() {
+        let pool = pool().await;
+        for importer in get_importers() {
+            let columns = importer.get_columns();
+            let headers = StringRecord::from(columns.to_vec());
+            let row = StringRecord::from(vec!["not an id"; columns.len()]);
+            let mut quarantine = Quarantine::default();
+            importer
+                .write_batch(&pool, &headers, vec![row.clone(), row], 1, &mut quarantine)
+                .await
+                .unwrap_or_else(|error| panic!("{}: {error:#}", importer.get_name()));
+            assert_eq!(quarantine.count(), 2, "{}", importer.get_name());
+        }
+    }
+}
@@ -1,4 +1,10 @@
-use crate::{id::Id, sync::importers::Importer};
+use crate::{
+    id::Id,
+    sync::{
+        importers::{deserialize_rows, Importer},
+        quarantine::Quarantine,
+    },
+};
 use anyhow::Result;
 use csv_async::StringRecord;
 use serde::Deserialize;
@@ -21,12 +27,16 @@ impl Importer for RatingsImporter {
         "title.ratings.tsv.gz"
     }
 
-    fn get_url(&self) -> &str {
-        "https://datasets.imdbws.com/title.ratings.tsv.gz"
+    fn get_version(&self) -> u32 {
+        0
+    }
+
+    fn get_columns(&self) -> &[&str] {
+        &["tconst", "averageRating", "numVotes"]
     }
 
     fn get_bind_count(&self) -> usize {
-        3
+        4
     }
 
     async fn write_batch(
@@ -34,22 +44,47 @@ impl Importer for RatingsImporter {
         pool: &SqlitePool,
         headers: &StringRecord,
         rows: Vec<StringRecord>,
+        import_id: i64,
+        quarantine: &mut Quarantine,
     ) -> Result<()> {
-        let mut qb =
-            QueryBuilder::new("INSERT OR REPLACE INTO ratings (id, average_rating, num_votes) ");
-        let rows: Vec<RatingsRow> = rows
-            .into_iter()
-            .filter_map(|row| row.deserialize(Some(headers)).ok())
-            .collect();
+        let mut qb = QueryBuilder::new(
+            "INSERT OR REPLACE INTO ratings (id, average_rating, num_votes, import_id) ",
+        );
+        let rows: Vec<RatingsRow> = deserialize_rows(headers, rows, quarantine);
+        // every row of the batch was rejected, and push_values needs at least one row
+        if rows.is_empty() {
+            return Ok(());
+        }
 
         qb.push_values(rows, |mut qb, row| {
             qb.push_bind(row.tconst.get())
                 .push_bind(row.average_rating)
-                .push_bind(row.num_votes);
+                .push_bind(row.num_votes)
+                .push_bind(import_id);
         });
 
         let query = qb.build();
         query.execute(pool).await?;
         Ok(())
     }
+
+    async fn remove_stale(&self, pool: &SqlitePool, import_id: i64) -> Result<u64> {
+        let result = sqlx::query!("DELETE FROM ratings WHERE import_id != ?", import_id)
+            .execute(pool)
+            .await?;
+        Ok(result.rows_affected())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sync::importers::parse_sample;
+
+    #[test]
+    fn columns_match_row() {
+        let row: RatingsRow = parse_sample(&RatingsImporter, &["tt0000001", "5.7", "2166"]);
+        assert_eq!(row.average_rating, 5.7);
+        assert_eq!(row.num_votes, 2166);
+    }
 }
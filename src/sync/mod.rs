@@ -1,143 +1,357 @@
-use crate::sync::{
-    ensure_file::ensure_file, file_meta::FileMeta, import_file::import_file,
-    importers::get_importers,
-};
-use anyhow::Result;
-use futures::future::try_join_all;
-use std::{
-    path::PathBuf,
+use crate::{
+    db::Database,
     sync::{
-        atomic::{AtomicBool, Ordering},
-        Arc,
+        absolute_numbers::rebuild_absolute_numbers,
+        charts::rebuild_charts,
+        ensure_file::ensure_file,
+        file_meta::FileMeta,
+        import_file::import_file,
+        importers::{get_importers, Importer},
     },
-    time::Instant,
 };
-use tokio::{
-    sync::{Mutex, Notify},
-    task::JoinHandle,
+use anyhow::Result;
+use futures::future::join_all;
+use sqlx::SqlitePool;
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
 };
+use tokio::task::JoinHandle;
 
+mod absolute_numbers;
+mod charts;
 mod ensure_file;
 mod file_meta;
 mod import_file;
 mod importers;
 mod nullable;
+mod quarantine;
+mod s3;
+mod scheduler;
+mod snapshot;
+mod source;
+
+pub use scheduler::run_sync_scheduler;
+pub use snapshot::SnapshotTarget;
+pub use source::DatasetSource;
+
+/// The directory holding the database and downloaded datasets, shared with GraphQL resolvers.
+pub struct DataDir(pub PathBuf);
+
+fn get_cache_dir(data_dir: &Path) -> PathBuf {
+    data_dir.join("cache")
+}
 
-pub async fn sync_data(data_dir: &PathBuf, pool: &sqlx::SqlitePool) -> Result<()> {
-    let importers = get_importers();
-    let cache_dir = data_dir.join("cache");
+/// Metadata for every dataset file, including ones that were never downloaded.
+pub fn get_file_metas(data_dir: &Path) -> Result<Vec<FileMeta>> {
+    let cache_dir = get_cache_dir(data_dir);
+    get_importers()
+        .iter()
+        .map(|importer| FileMeta::new(&cache_dir.join(importer.get_name())))
+        .collect()
+}
+
+/// Fraction of rows per file that can fail to parse before its import fails, see `Quarantine`
+pub const DEFAULT_MAX_REJECTED_RATE: f64 = 0.01;
+
+/// Attempts per file before a sync gives up on it
+const MAX_FILE_ATTEMPTS: u32 = 3;
+/// Delay before the second attempt, doubled for every attempt after that
+const FILE_RETRY_DELAY: Duration = Duration::from_secs(30);
+
+/// Tracks attempts for one step of syncing a file, see `MAX_FILE_ATTEMPTS`.
+struct Retries<'a> {
+    file_name: &'a str,
+    attempt: u32,
+}
+
+impl<'a> Retries<'a> {
+    fn new(file_name: &'a str) -> Self {
+        Self {
+            file_name,
+            attempt: 1,
+        }
+    }
+
+    /// Wait before the next attempt, or return the error if there are no attempts left.
+    async fn backoff(&mut self, error: anyhow::Error) -> Result<()> {
+        if self.attempt >= MAX_FILE_ATTEMPTS {
+            return Err(error);
+        }
+
+        let delay = FILE_RETRY_DELAY * 2u32.pow(self.attempt - 1);
+        tracing::warn!(
+            file = self.file_name,
+            attempt = self.attempt,
+            ?delay,
+            "{error:#}, retrying"
+        );
+        tokio::time::sleep(delay).await;
+        self.attempt += 1;
+        Ok(())
+    }
+}
+
+/// Download every dataset that changed and import them into a new database version, which replaces
+/// the live one once it's complete and valid. Returns whether anything was imported.
+/// `force` downloads and imports everything again.
+pub async fn sync_data(
+    data_dir: &Path,
+    db: &Database,
+    source: &DatasetSource,
+    force: bool,
+    max_rejected_rate: f64,
+) -> Result<bool> {
+    let cache_dir = get_cache_dir(data_dir);
 
     // ensure data dir exits
-    std::fs::create_dir_all(&cache_dir).unwrap();
-
-    let mut tasks: Vec<JoinHandle<Result<()>>> = Vec::new();
-    let basics_done = Arc::new(Mutex::new((false, Arc::new(Notify::new()))));
-    let needs_search_update = Arc::new(AtomicBool::new(false));
-    for importer in importers {
-        let pool = pool.clone();
-        let basics_done = basics_done.clone();
-        let cache_dir = cache_dir.clone();
-        let needs_search_update = needs_search_update.clone();
+    std::fs::create_dir_all(&cache_dir)?;
+
+    let mut tasks: Vec<JoinHandle<Result<FileMeta>>> = Vec::new();
+    for importer in get_importers() {
+        let file_path = cache_dir.join(importer.get_name());
+        let source = source.clone();
         tasks.push(tokio::spawn(async move {
             let file_name = importer.get_name();
-            let file_path = cache_dir.join(file_name);
             let mut meta = FileMeta::new(&file_path)?;
 
-            ensure_file(&mut meta, importer.get_url()).await?;
-
-            let is_basics = file_name == "title.basics.tsv.gz";
-            let is_akas = file_name == "title.akas.tsv.gz";
-            if !is_basics {
-                let basics_done = basics_done.lock().await;
-                if !basics_done.0 {
-                    let notifier = basics_done.1.clone();
-                    drop(basics_done); // or else we hold the lock while waiting
-                    tracing::debug!(file = file_name, "waiting for basics to finish");
-                    notifier.notified().await;
-                    tracing::debug!(file = file_name, "waited for basics");
+            let mut retries = Retries::new(file_name);
+            while let Err(error) = ensure_file(&mut meta, &source, file_name, force).await {
+                if let Err(error) = retries.backoff(error).await {
+                    record_failure(&mut meta, &error)?;
+                    return Err(error);
                 }
             }
 
-            if !meta.imported_at.is_some() {
-                import_file(&pool, &importer, &mut meta).await?;
-                if is_basics || is_akas {
-                    needs_search_update.store(true, Ordering::Relaxed);
-                }
+            // a file that still has to be imported keeps its error until that worked
+            if meta.imported_at.is_some() && meta.last_error.is_some() {
+                meta.last_error = None;
+                meta.save()?;
             }
 
-            if is_basics {
-                let mut basics_done = basics_done.lock().await;
-                basics_done.0 = true;
-                basics_done.1.notify_waiters();
+            Ok(meta)
+        }))
+    }
+
+    let mut failed = Vec::new();
+    let mut pending = Vec::new();
+    for (task, importer) in join_all(tasks).await.into_iter().zip(get_importers()) {
+        match task {
+            // data written by an older version of the importer is imported again
+            Ok(Ok(meta))
+                if meta.imported_at.is_none() || meta.import_version < importer.get_version() =>
+            {
+                pending.push((importer, meta))
+            }
+            Ok(Ok(_)) => {}
+            Ok(Err(_)) => failed.push(importer.get_name().to_string()),
+            Err(error) => {
+                tracing::error!(
+                    file = importer.get_name(),
+                    "download task panicked: {error}"
+                );
+                failed.push(importer.get_name().to_string());
+            }
+        }
+    }
+
+    // the other datasets reference titles, so they wait until basics can be imported with them
+    if failed.iter().any(|name| name == "title.basics.tsv.gz") && !pending.is_empty() {
+        tracing::warn!("not importing other datasets because title.basics.tsv.gz failed to sync");
+        pending.clear();
+    }
+
+    let is_pending = |name: &str| {
+        pending
+            .iter()
+            .any(|(importer, _): &(Box<dyn Importer>, FileMeta)| importer.get_name() == name)
+    };
+    let needs_search_update = is_pending("title.basics.tsv.gz") || is_pending("title.akas.tsv.gz");
+    let needs_chart_update =
+        is_pending("title.basics.tsv.gz") || is_pending("title.ratings.tsv.gz");
+    // removing stale titles also removes their episodes, which shifts the numbers of the rest
+    let needs_absolute_number_update =
+        is_pending("title.basics.tsv.gz") || is_pending("title.episode.tsv.gz");
+
+    // chart_scores is empty on the first run after it was added, even if nothing was imported
+    let has_chart_scores = sqlx::query_scalar!("SELECT EXISTS (SELECT 1 FROM chart_scores)")
+        .fetch_one(&db.current().pool)
+        .await?
+        == 1;
+
+    if !pending.is_empty() || !has_chart_scores {
+        let build = db.create_build().await?;
+        let result = async {
+            let import_failed = import_pending(&build.pool, &mut pending, max_rejected_rate).await;
+            if !import_failed.is_empty() {
+                failed.extend(import_failed);
+                anyhow::bail!("not swapping in a database with incomplete imports");
+            }
+
+            if needs_search_update {
+                rebuild_search_index(&build.pool).await?;
+            }
+
+            if needs_chart_update || !has_chart_scores {
+                rebuild_charts(&build.pool).await?;
+            }
+
+            if needs_absolute_number_update {
+                rebuild_absolute_numbers(&build.pool).await?;
             }
 
             Ok(())
-        }))
+        }
+        .await;
+
+        match result {
+            Ok(()) => {
+                db.swap(build).await?;
+                // the pending files are live now, even if other files failed to download
+                for (importer, meta) in &mut pending {
+                    meta.imported_at = Some(chrono::Utc::now());
+                    meta.import_version = importer.get_version();
+                    meta.last_error = None;
+                    meta.save()?;
+                }
+            }
+            Err(error) => {
+                db.discard(build).await?;
+                if failed.is_empty() {
+                    return Err(error);
+                }
+                tracing::error!("{error:#}");
+            }
+        }
     }
 
-    try_join_all(tasks).await?;
-
-    if needs_search_update.load(Ordering::Relaxed) {
-        // update the search index
-        tracing::info!("rebuilding search index");
-        let start = Instant::now();
-        let mut tx = pool.begin().await?;
-
-        // Clear the search index
-        sqlx::query!("DELETE FROM search_index")
-            .execute(&mut *tx)
-            .await?;
-
-        // Insert deduplicated titles with priority for primary titles over AKAs
-        sqlx::query!(
-            "WITH combined_titles AS (
-                -- Primary titles (highest priority)
-                SELECT 
-                    primary_title as text, 
-                    1 as is_display, 
-                    id as title_id, 
-                    0 as ordering,
-                    1 as priority
-                FROM titles 
-                WHERE primary_title IS NOT NULL AND primary_title != ''
-                
-                UNION ALL
-                
-                -- AKA titles (lower priority, ordered by their original ordering)
-                SELECT 
-                    title as text, 
-                    0 as is_display, 
-                    id as title_id, 
-                    ordering,
-                    2 as priority
-                FROM akas 
-                WHERE title IS NOT NULL AND title != ''
-            ),
-            deduplicated AS (
-                SELECT 
-                    text, 
-                    is_display, 
-                    title_id, 
-                    ordering,
-                    ROW_NUMBER() OVER (
-                        PARTITION BY text, title_id 
-                        ORDER BY priority ASC, ordering ASC
-                    ) as rn
-                FROM combined_titles
-            )
-            INSERT INTO search_index (text, is_display, title_id, ordering)
-            SELECT text, is_display, title_id, ordering
-            FROM deduplicated
-            WHERE rn = 1"
-        )
+    if !failed.is_empty() {
+        anyhow::bail!("failed to sync {}", failed.join(", "));
+    }
+
+    tracing::info!("up to date");
+    Ok(!pending.is_empty())
+}
+
+/// Import files into a build, basics first since the other datasets reference its titles.
+/// Returns the names of files that failed to import.
+async fn import_pending(
+    pool: &SqlitePool,
+    pending: &mut [(Box<dyn Importer>, FileMeta)],
+    max_rejected_rate: f64,
+) -> Vec<String> {
+    let (basics, others): (Vec<_>, Vec<_>) = pending
+        .iter_mut()
+        .partition(|(importer, _)| importer.get_name() == "title.basics.tsv.gz");
+
+    for (importer, meta) in basics {
+        if import_with_retries(pool, importer.as_ref(), meta, max_rejected_rate)
+            .await
+            .is_err()
+        {
+            return vec![importer.get_name().to_string()];
+        }
+    }
+
+    join_all(others.into_iter().map(|(importer, meta)| async move {
+        let result = import_with_retries(pool, importer.as_ref(), meta, max_rejected_rate).await;
+        result.err().map(|_| importer.get_name().to_string())
+    }))
+    .await
+    .into_iter()
+    .flatten()
+    .collect()
+}
+
+async fn import_with_retries(
+    pool: &SqlitePool,
+    importer: &dyn Importer,
+    meta: &mut FileMeta,
+    max_rejected_rate: f64,
+) -> Result<()> {
+    let mut retries = Retries::new(importer.get_name());
+    loop {
+        match import_file(pool, importer, &meta.path, max_rejected_rate).await {
+            Ok(rejected) => {
+                meta.rejected_rows = Some(rejected);
+                return Ok(());
+            }
+            Err(error) => {
+                if let Err(error) = retries.backoff(error).await {
+                    record_failure(meta, &error)?;
+                    return Err(error);
+                }
+            }
+        }
+    }
+}
+
+/// Log a file's error and keep it in its metadata for `Query.stats`.
+fn record_failure(meta: &mut FileMeta, error: &anyhow::Error) -> Result<()> {
+    let file_name = meta.path.file_name().unwrap_or_default().to_string_lossy();
+    tracing::error!(file = %file_name, "sync failed: {error:#}");
+    meta.last_error = Some(format!("{error:#}"));
+    meta.failed_at = Some(chrono::Utc::now());
+    meta.save()
+}
+
+async fn rebuild_search_index(pool: &SqlitePool) -> Result<()> {
+    tracing::info!("rebuilding search index");
+    let start = Instant::now();
+    let mut tx = pool.begin().await?;
+
+    // Clear the search index
+    sqlx::query!("DELETE FROM search_index")
         .execute(&mut *tx)
         .await?;
 
-        tracing::debug!("committing search index changes");
-        tx.commit().await?;
-        tracing::info!("search index rebuild complete in {:?}", start.elapsed());
-    }
+    // Insert deduplicated titles with priority for primary titles over AKAs
+    sqlx::query!(
+        "WITH combined_titles AS (
+            -- Primary titles (highest priority)
+            SELECT 
+                primary_title as text, 
+                1 as is_display, 
+                id as title_id, 
+                0 as ordering,
+                1 as priority
+            FROM titles 
+            WHERE primary_title IS NOT NULL AND primary_title != ''
+    
+            UNION ALL
+    
+            -- AKA titles (lower priority, ordered by their original ordering)
+            SELECT 
+                title as text, 
+                0 as is_display, 
+                id as title_id, 
+                ordering,
+                2 as priority
+            FROM akas 
+            WHERE title IS NOT NULL AND title != ''
+        ),
+        deduplicated AS (
+            SELECT 
+                text, 
+                is_display, 
+                title_id, 
+                ordering,
+                ROW_NUMBER() OVER (
+                    PARTITION BY text, title_id 
+                    ORDER BY priority ASC, ordering ASC
+                ) as rn
+            FROM combined_titles
+        )
+        INSERT INTO search_index (text, is_display, title_id, ordering)
+        SELECT text, is_display, title_id, ordering
+        FROM deduplicated
+        WHERE rn = 1"
+    )
+    .execute(&mut *tx)
+    .await?;
 
-    tracing::info!("up to date");
+    tracing::debug!("committing search index changes");
+    tx.commit().await?;
+    tracing::info!("search index rebuild complete in {:?}", start.elapsed());
     Ok(())
 }
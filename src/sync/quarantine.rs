@@ -0,0 +1,101 @@
+use anyhow::Result;
+use csv_async::StringRecord;
+use serde::Serialize;
+use std::{
+    collections::BTreeSet,
+    fmt::Display,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+/// Rejected rows kept per file, the rest are only counted
+const MAX_SAMPLES: usize = 100;
+
+/// Rows of one import that failed to parse. Every row is counted, the first `MAX_SAMPLES`
+/// are written next to the dataset as `title.basics.tsv.rejected.jsonl`.
+#[derive(Default)]
+pub struct Quarantine {
+    rejected: u64,
+    samples: Vec<RejectedRow>,
+    /// Problems with rows that are still imported, like a genre hubble doesn't know
+    warnings: BTreeSet<String>,
+}
+
+#[derive(Serialize)]
+struct RejectedRow {
+    /// Line in the decompressed file, 1 is the header
+    line: Option<u64>,
+    error: String,
+    /// The row with its fields joined by tabs, missing if it couldn't be read at all
+    row: Option<String>,
+}
+
+pub fn get_quarantine_path(file_path: &Path) -> PathBuf {
+    file_path.with_extension("rejected.jsonl")
+}
+
+impl Quarantine {
+    /// Reject a row that was read but doesn't deserialize.
+    pub fn reject_row(&mut self, row: &StringRecord, error: impl Display) {
+        let line = row.position().map(|position| position.line());
+        let fields = row.iter().collect::<Vec<_>>().join("\t");
+        self.reject(line, error, Some(fields));
+    }
+
+    /// Reject a row the CSV reader couldn't read, like one with the wrong number of fields.
+    pub fn reject_error(&mut self, error: &csv_async::Error) {
+        let line = error.position().map(|position| position.line());
+        self.reject(line, error, None);
+    }
+
+    fn reject(&mut self, line: Option<u64>, error: impl Display, row: Option<String>) {
+        self.rejected += 1;
+        if self.samples.len() < MAX_SAMPLES {
+            self.samples.push(RejectedRow {
+                line,
+                error: error.to_string(),
+                row,
+            });
+        }
+    }
+
+    pub fn count(&self) -> u64 {
+        self.rejected
+    }
+
+    /// Warn about a row that is imported anyway. Each distinct message is logged once per import,
+    /// since a new value usually shows up on many rows.
+    pub fn warn(&mut self, message: String) {
+        self.warnings.insert(message);
+    }
+
+    pub fn warnings(&self) -> impl Iterator<Item = &str> {
+        self.warnings.iter().map(String::as_str)
+    }
+
+    /// Write the samples for the dataset at `file_path`, or remove the ones of an earlier import
+    /// if nothing was rejected this time.
+    pub fn save(&self, file_path: &Path) -> Result<()> {
+        let path = get_quarantine_path(file_path);
+        if self.samples.is_empty() {
+            match std::fs::remove_file(&path) {
+                Err(error) if error.kind() != std::io::ErrorKind::NotFound => {
+                    return Err(error.into())
+                }
+                _ => return Ok(()),
+            }
+        }
+
+        // rows that couldn't be read are rejected before the rest of their batch is parsed
+        let mut samples = self.samples.iter().collect::<Vec<_>>();
+        samples.sort_by_key(|sample| sample.line);
+
+        let mut file = std::io::BufWriter::new(std::fs::File::create(&path)?);
+        for sample in samples {
+            serde_json::to_writer(&mut file, sample)?;
+            file.write_all(b"\n")?;
+        }
+        file.flush()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,426 @@
+use anyhow::{Context, Result};
+use hmac::{Hmac, Mac};
+use reqwest::{Client, Method, RequestBuilder, Response};
+use sha2::{Digest, Sha256};
+use std::{env, fmt, path::Path};
+use tokio::io::AsyncReadExt;
+
+/// Sent instead of a body hash, so large uploads don't have to be hashed up front
+const UNSIGNED_PAYLOAD: &str = "UNSIGNED-PAYLOAD";
+/// Snapshots are uploaded in parts of this size, S3 allows at most 10,000 parts
+const PART_SIZE: usize = 64 * 1024 * 1024;
+
+/// A bucket on S3 or an S3-compatible store like MinIO, with requests signed using AWS Signature Version 4.
+///
+/// Requests use path-style URLs (`{endpoint}/{bucket}/{key}`), which MinIO requires and AWS still supports.
+#[derive(Clone)]
+pub struct S3Bucket {
+    endpoint: String,
+    region: String,
+    bucket: String,
+    access_key: String,
+    secret_key: String,
+    /// Set for temporary credentials, like the ones of an assumed role
+    session_token: Option<String>,
+}
+
+impl fmt::Debug for S3Bucket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("S3Bucket")
+            .field("endpoint", &self.endpoint)
+            .field("region", &self.region)
+            .field("bucket", &self.bucket)
+            .finish_non_exhaustive()
+    }
+}
+
+impl S3Bucket {
+    /// Configure a bucket from `S3_ENDPOINT`, `AWS_REGION`, `AWS_ACCESS_KEY_ID`, `AWS_SECRET_ACCESS_KEY` and
+    /// `AWS_SESSION_TOKEN`. The endpoint defaults to AWS for the region, and the region to us-east-1.
+    pub fn from_env(bucket: &str) -> Result<Self> {
+        let region = env::var("AWS_REGION").unwrap_or("us-east-1".to_string());
+        let endpoint =
+            env::var("S3_ENDPOINT").unwrap_or(format!("https://s3.{region}.amazonaws.com"));
+        Ok(Self {
+            endpoint: endpoint.trim_end_matches('/').to_string(),
+            region,
+            bucket: bucket.to_string(),
+            access_key: env::var("AWS_ACCESS_KEY_ID").context("AWS_ACCESS_KEY_ID must be set")?,
+            secret_key: env::var("AWS_SECRET_ACCESS_KEY")
+                .context("AWS_SECRET_ACCESS_KEY must be set")?,
+            session_token: env::var("AWS_SESSION_TOKEN").ok(),
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.bucket
+    }
+
+    /// A signed GET request for the object. Headers added afterwards, like `If-None-Match` or `Range`,
+    /// are left out of the signature, which S3 allows.
+    pub fn get(&self, client: &Client, key: &str) -> Result<RequestBuilder> {
+        self.request(client, Method::GET, key, &[])
+    }
+
+    /// Upload a file with a multipart upload, so it can be larger than the 5 GB limit of a single PUT.
+    pub async fn upload_file(&self, client: &Client, key: &str, path: &Path) -> Result<()> {
+        let response = self
+            .request(client, Method::POST, key, &[("uploads", "")])?
+            .send()
+            .await?;
+        let body = check_response(response).await?.text().await?;
+        let upload_id = xml_value(&body, "UploadId")
+            .context("CreateMultipartUpload response has no UploadId")?
+            .to_string();
+
+        match self.upload_parts(client, key, &upload_id, path).await {
+            Ok(parts) => {
+                let mut body = String::from("<CompleteMultipartUpload>");
+                for (number, etag) in parts.iter().enumerate() {
+                    body.push_str(&format!(
+                        "<Part><PartNumber>{}</PartNumber><ETag>{etag}</ETag></Part>",
+                        number + 1
+                    ));
+                }
+                body.push_str("</CompleteMultipartUpload>");
+
+                let response = self
+                    .request(client, Method::POST, key, &[("uploadId", &upload_id)])?
+                    .body(body)
+                    .send()
+                    .await?;
+                // S3 can report errors with a 200 status once it started sending the response
+                let body = check_response(response).await?.text().await?;
+                if body.contains("<Error>") {
+                    anyhow::bail!("CompleteMultipartUpload failed: {body}");
+                }
+                Ok(())
+            }
+            Err(error) => {
+                // don't leave the parts around, they are billed until the upload is aborted
+                let abort = self.request(client, Method::DELETE, key, &[("uploadId", &upload_id)]);
+                if let Ok(abort) = abort {
+                    let _ = abort.send().await;
+                }
+                Err(error)
+            }
+        }
+    }
+
+    /// Upload every part of the file, returning the ETag of each part in order.
+    async fn upload_parts(
+        &self,
+        client: &Client,
+        key: &str,
+        upload_id: &str,
+        path: &Path,
+    ) -> Result<Vec<String>> {
+        let mut file = tokio::fs::File::open(path).await?;
+        let mut etags = Vec::new();
+        loop {
+            let mut part = Vec::with_capacity(PART_SIZE);
+            (&mut file)
+                .take(PART_SIZE as u64)
+                .read_to_end(&mut part)
+                .await?;
+            // every upload has at least one part, even if the file is empty
+            if part.is_empty() && !etags.is_empty() {
+                break;
+            }
+
+            let is_last = part.len() < PART_SIZE;
+            let number = (etags.len() + 1).to_string();
+            let response = self
+                .request(
+                    client,
+                    Method::PUT,
+                    key,
+                    &[("partNumber", &number), ("uploadId", upload_id)],
+                )?
+                .body(part)
+                .send()
+                .await?;
+            let response = check_response(response).await?;
+            let etag = response
+                .headers()
+                .get("ETag")
+                .and_then(|v| v.to_str().ok())
+                .context("UploadPart response has no ETag")?;
+            etags.push(etag.to_string());
+
+            if is_last {
+                break;
+            }
+        }
+
+        Ok(etags)
+    }
+
+    fn request(
+        &self,
+        client: &Client,
+        method: Method,
+        key: &str,
+        query: &[(&str, &str)],
+    ) -> Result<RequestBuilder> {
+        let path = format!(
+            "/{}/{}",
+            uri_encode(&self.bucket, true),
+            uri_encode(key, false)
+        );
+        let query = canonical_query(query);
+        let url = if query.is_empty() {
+            format!("{}{path}", self.endpoint)
+        } else {
+            format!("{}{path}?{query}", self.endpoint)
+        };
+        let parsed_url = reqwest::Url::parse(&url)?;
+        let host = parsed_url.host_str().context("S3 endpoint has no host")?;
+        // must match the Host header reqwest sends, which only includes non-default ports
+        let host = match parsed_url.port() {
+            Some(port) => format!("{host}:{port}"),
+            None => host.to_string(),
+        };
+
+        let amz_date = chrono::Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+        let mut headers = vec![
+            ("host", host.as_str()),
+            ("x-amz-content-sha256", UNSIGNED_PAYLOAD),
+            ("x-amz-date", amz_date.as_str()),
+        ];
+        if let Some(token) = &self.session_token {
+            headers.push(("x-amz-security-token", token));
+        }
+        let authorization = self.authorization(
+            "s3",
+            method.as_str(),
+            &path,
+            &query,
+            &headers,
+            UNSIGNED_PAYLOAD,
+        );
+
+        let mut request = client.request(method, url);
+        // reqwest sets Host itself
+        for (name, value) in &headers[1..] {
+            request = request.header(*name, *value);
+        }
+        Ok(request.header("Authorization", authorization))
+    }
+
+    /// The `Authorization` header for a request. `path` and `query` must already be URI-encoded, `headers`
+    /// are the signed headers with lowercase names sorted by name, and must include `x-amz-date`.
+    fn authorization(
+        &self,
+        service: &str,
+        method: &str,
+        path: &str,
+        query: &str,
+        headers: &[(&str, &str)],
+        payload_hash: &str,
+    ) -> String {
+        let amz_date = headers
+            .iter()
+            .find(|(name, _)| *name == "x-amz-date")
+            .map(|(_, value)| *value)
+            .expect("x-amz-date is signed");
+        let date = &amz_date[..8];
+        let scope = format!("{date}/{}/{service}/aws4_request", self.region);
+
+        let canonical_headers = headers
+            .iter()
+            .map(|(name, value)| format!("{name}:{}\n", value.trim()))
+            .collect::<String>();
+        let signed_headers = headers
+            .iter()
+            .map(|(name, _)| *name)
+            .collect::<Vec<_>>()
+            .join(";");
+        let canonical_request = format!(
+            "{method}\n{path}\n{query}\n{canonical_headers}\n{signed_headers}\n{payload_hash}"
+        );
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{amz_date}\n{scope}\n{}",
+            hex::encode(Sha256::digest(canonical_request.as_bytes()))
+        );
+
+        let signing_key = [self.region.as_str(), service, "aws4_request"].iter().fold(
+            hmac_sha256(
+                format!("AWS4{}", self.secret_key).as_bytes(),
+                date.as_bytes(),
+            ),
+            |key, part| hmac_sha256(&key, part.as_bytes()),
+        );
+        let signature = hex::encode(hmac_sha256(&signing_key, string_to_sign.as_bytes()));
+        format!(
+            "AWS4-HMAC-SHA256 Credential={}/{scope}, SignedHeaders={signed_headers}, Signature={signature}",
+            self.access_key
+        )
+    }
+}
+
+/// Encode and sort query parameters the way they are signed, which is also how they are sent.
+fn canonical_query(query: &[(&str, &str)]) -> String {
+    let mut query = query
+        .iter()
+        .map(|(k, v)| (uri_encode(k, true), uri_encode(v, true)))
+        .collect::<Vec<_>>();
+    query.sort();
+    query
+        .iter()
+        .map(|(k, v)| format!("{k}={v}"))
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// Percent-encode everything except unreserved characters, and `/` too if `encode_slash` is set.
+fn uri_encode(value: &str, encode_slash: bool) -> String {
+    let mut encoded = String::new();
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                encoded.push(byte as char)
+            }
+            b'/' if !encode_slash => encoded.push('/'),
+            _ => encoded.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    encoded
+}
+
+/// The text of the first `<tag>` in an XML response, S3 responses are simple enough to skip a parser.
+fn xml_value<'a>(xml: &'a str, tag: &str) -> Option<&'a str> {
+    let start = xml.find(&format!("<{tag}>"))? + tag.len() + 2;
+    let end = xml[start..].find(&format!("</{tag}>"))? + start;
+    Some(&xml[start..end])
+}
+
+/// Turn error statuses into errors that include S3's error message.
+pub async fn check_response(response: Response) -> Result<Response> {
+    let status = response.status();
+    if status.is_client_error() || status.is_server_error() {
+        let url = response.url().clone();
+        let body = response.text().await.unwrap_or_default();
+        let message = xml_value(&body, "Message").unwrap_or(&body);
+        anyhow::bail!("S3 request to {url} failed with {status}: {message}");
+    }
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EMPTY_PAYLOAD: &str = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+    const SESSION_TOKEN: &str = "AQoDYXdzEPT//////////wEXAMPLEtc764bNrC9SAPBSM22wDOk4x4HIZ8j4FZTwdQWLWsKWHGBuFqwAeMicRXmxfpSPfIeoIYRqTflfKD8YUuwthAx7mSEI/qkPpKPi/kMcGdQrmGdeehM4IC1NtBmUpp2wUE8phUZampKsburEDy0KPkyQDYwT7WZ0wq5VSXDvp75YU9HFvlRd8Tx6q6fE8YQcHNVXAkiY9q6d+xo0rKwT38xVqr7ZD0u0iPPkUL64lIZbqBAz+scqKmlzm8FDrypNC9Yjc8fPOLn9FX9KSYvKTr4rvx3iSIlTJabIQwj2ICCR/oLxBA==";
+
+    /// The credentials of AWS's SigV4 test suite
+    fn bucket(session_token: Option<&str>) -> S3Bucket {
+        S3Bucket {
+            endpoint: "https://example.amazonaws.com".to_string(),
+            region: "us-east-1".to_string(),
+            bucket: "bucket".to_string(),
+            access_key: "AKIDEXAMPLE".to_string(),
+            secret_key: "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY".to_string(),
+            session_token: session_token.map(str::to_string),
+        }
+    }
+
+    fn signature(authorization: &str) -> &str {
+        authorization.rsplit("Signature=").next().unwrap()
+    }
+
+    #[test]
+    fn signs_test_suite_requests() {
+        let headers = [
+            ("host", "example.amazonaws.com"),
+            ("x-amz-date", "20150830T123600Z"),
+        ];
+        let cases = [
+            // (test, method, path, query, signature)
+            (
+                "get-vanilla",
+                "GET",
+                "/".to_string(),
+                canonical_query(&[]),
+                "5fa00fa31553b73ebf1942676e86291e8372ff2a2260956d9b8aae1d763fbf31",
+            ),
+            (
+                "get-vanilla-query-order-key-case",
+                "GET",
+                "/".to_string(),
+                canonical_query(&[("Param2", "value2"), ("Param1", "value1")]),
+                "b97d918cfa904a5beff61c982a1b6f458b799221646efd99d3219ec94cdf2500",
+            ),
+            (
+                "get-space-normalized",
+                "GET",
+                uri_encode("/example space/", false),
+                canonical_query(&[]),
+                "652487583200325589f1fba4c7e578f72c47cb61beeca81406b39ddec1366741",
+            ),
+        ];
+
+        let bucket = bucket(None);
+        for (test, method, path, query, expected) in cases {
+            let authorization =
+                bucket.authorization("service", method, &path, &query, &headers, EMPTY_PAYLOAD);
+            assert_eq!(signature(&authorization), expected, "{test}");
+        }
+
+        let authorization =
+            bucket.authorization("service", "GET", "/", "", &headers, EMPTY_PAYLOAD);
+        assert_eq!(
+            authorization,
+            "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20150830/us-east-1/service/aws4_request, SignedHeaders=host;x-amz-date, Signature=5fa00fa31553b73ebf1942676e86291e8372ff2a2260956d9b8aae1d763fbf31"
+        );
+    }
+
+    #[test]
+    fn signs_session_token() {
+        // post-sts-header-before
+        let headers = [
+            ("host", "example.amazonaws.com"),
+            ("x-amz-date", "20150830T123600Z"),
+            ("x-amz-security-token", SESSION_TOKEN),
+        ];
+        let authorization = bucket(Some(SESSION_TOKEN)).authorization(
+            "service",
+            "POST",
+            "/",
+            "",
+            &headers,
+            EMPTY_PAYLOAD,
+        );
+        assert_eq!(
+            signature(&authorization),
+            "85d96828115b5dc0cfc3bd16ad9e210dd772bbebba041836c64533a82be05ead"
+        );
+    }
+
+    #[test]
+    fn sends_session_token() {
+        let request = bucket(Some(SESSION_TOKEN))
+            .get(&Client::new(), "title.basics.tsv.gz")
+            .unwrap()
+            .build()
+            .unwrap();
+        assert_eq!(request.headers()["x-amz-security-token"], SESSION_TOKEN);
+        let authorization = request.headers()["Authorization"].to_str().unwrap();
+        assert!(authorization
+            .contains("SignedHeaders=host;x-amz-content-sha256;x-amz-date;x-amz-security-token,"));
+
+        let request = bucket(None)
+            .get(&Client::new(), "title.basics.tsv.gz")
+            .unwrap()
+            .build()
+            .unwrap();
+        assert!(!request.headers().contains_key("x-amz-security-token"));
+    }
+}
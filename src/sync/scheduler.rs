@@ -0,0 +1,70 @@
+use super::{
+    snapshot::{upload_snapshot, SnapshotTarget},
+    sync_data, DatasetSource,
+};
+use crate::db::Database;
+use std::{path::PathBuf, sync::Arc, time::Duration};
+
+/// How often to check for new datasets after a successful sync
+const SYNC_INTERVAL: Duration = Duration::from_secs(4 * 60 * 60);
+/// Delay after the first failed sync, doubled for every consecutive failure up to `SYNC_INTERVAL`
+const FAILURE_DELAY: Duration = Duration::from_secs(60);
+
+/// Sync forever. Each sync runs in its own task so errors and panics are logged and retried
+/// with exponential backoff instead of ending syncing for the rest of the process.
+/// With `force`, syncs re-download and re-import every file until one succeeds.
+/// With a `snapshot` target, the database is uploaded after the first sync and every sync that imported something.
+pub async fn run_sync_scheduler(
+    data_dir: PathBuf,
+    db: Arc<Database>,
+    source: DatasetSource,
+    mut force: bool,
+    snapshot: Option<SnapshotTarget>,
+    max_rejected_rate: f64,
+) {
+    tracing::info!("syncing datasets from {source}");
+    let mut failures = 0;
+    let mut needs_snapshot = true;
+    loop {
+        let task_data_dir = data_dir.clone();
+        let task_db = db.clone();
+        let source = source.clone();
+        let result = tokio::spawn(async move {
+            sync_data(&task_data_dir, &task_db, &source, force, max_rejected_rate).await
+        })
+        .await;
+        let error = match result {
+            Ok(Ok(imported)) => {
+                needs_snapshot |= imported;
+                None
+            }
+            Ok(Err(error)) => Some(format!("{error:#}")),
+            Err(error) => Some(format!("sync panicked: {error}")),
+        };
+
+        let delay = match error {
+            None => {
+                failures = 0;
+                force = false;
+                if let Some(target) = snapshot.as_ref().filter(|_| needs_snapshot) {
+                    // a failed upload is retried after the next sync, the local database is fine
+                    match upload_snapshot(&db.current().pool, &data_dir, target).await {
+                        Ok(()) => needs_snapshot = false,
+                        Err(error) => tracing::error!("snapshot upload failed: {error:#}"),
+                    }
+                }
+                SYNC_INTERVAL
+            }
+            Some(error) => {
+                failures += 1;
+                let delay = FAILURE_DELAY
+                    .saturating_mul(2u32.saturating_pow(failures - 1))
+                    .min(SYNC_INTERVAL);
+                tracing::error!(failures, retry_in = ?delay, "{error}");
+                delay
+            }
+        };
+
+        tokio::time::sleep(delay).await;
+    }
+}
@@ -0,0 +1,65 @@
+use super::s3::S3Bucket;
+use anyhow::{Context, Result};
+use sqlx::SqlitePool;
+use std::{fmt, path::Path, time::Instant};
+
+/// Where to upload a copy of the database after a sync changed it, set with `SNAPSHOT_TARGET=s3://bucket/key`.
+#[derive(Debug, Clone)]
+pub struct SnapshotTarget {
+    bucket: S3Bucket,
+    key: String,
+}
+
+impl SnapshotTarget {
+    pub fn parse(value: &str) -> Result<Self> {
+        let location = value
+            .strip_prefix("s3://")
+            .with_context(|| format!("Snapshot target {value:?} must be an s3:// URL"))?;
+        let (bucket, key) = location
+            .split_once('/')
+            .filter(|(_, key)| !key.is_empty())
+            .with_context(|| format!("Snapshot target {value:?} has no object key"))?;
+
+        Ok(Self {
+            bucket: S3Bucket::from_env(bucket)?,
+            key: key.to_string(),
+        })
+    }
+}
+
+impl fmt::Display for SnapshotTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "s3://{}/{}", self.bucket.name(), self.key)
+    }
+}
+
+/// Write a consistent copy of the database with `VACUUM INTO` and upload it.
+pub async fn upload_snapshot(
+    pool: &SqlitePool,
+    data_dir: &Path,
+    target: &SnapshotTarget,
+) -> Result<()> {
+    let start = Instant::now();
+    let snapshot_path = data_dir.join("snapshot.db");
+    if snapshot_path.exists() {
+        tokio::fs::remove_file(&snapshot_path).await?;
+    }
+
+    tracing::info!("writing database snapshot");
+    sqlx::query("VACUUM INTO ?")
+        .bind(snapshot_path.to_string_lossy())
+        .execute(pool)
+        .await?;
+
+    tracing::info!("uploading database snapshot to {target}");
+    let client = reqwest::Client::new();
+    let result = target
+        .bucket
+        .upload_file(&client, &target.key, &snapshot_path)
+        .await;
+    tokio::fs::remove_file(&snapshot_path).await?;
+    result?;
+
+    tracing::info!("uploaded database snapshot in {:?}", start.elapsed());
+    Ok(())
+}
@@ -0,0 +1,61 @@
+use super::s3::S3Bucket;
+use anyhow::Result;
+use std::{fmt, path::PathBuf};
+
+pub const DEFAULT_BASE_URL: &str = "https://datasets.imdbws.com";
+
+/// Where dataset files like `title.basics.tsv.gz` are fetched from.
+#[derive(Debug, Clone)]
+pub enum DatasetSource {
+    /// A server laid out like datasets.imdbws.com, e.g. an internal mirror
+    Http { base_url: String },
+    /// A directory holding the dataset files, for offline use and tests
+    Local { dir: PathBuf },
+    /// Objects under a prefix in an S3-compatible bucket, see `S3Bucket::from_env` for configuration
+    S3 { bucket: S3Bucket, prefix: String },
+}
+
+impl Default for DatasetSource {
+    fn default() -> Self {
+        DatasetSource::Http {
+            base_url: DEFAULT_BASE_URL.to_string(),
+        }
+    }
+}
+
+impl DatasetSource {
+    /// Parse an `http(s)://` base URL, an `s3://bucket/prefix` URL, a `file://` URL or a plain directory path.
+    pub fn parse(value: &str) -> Result<Self> {
+        if let Some(location) = value.strip_prefix("s3://") {
+            let (bucket, prefix) = location.split_once('/').unwrap_or((location, ""));
+            Ok(DatasetSource::S3 {
+                bucket: S3Bucket::from_env(bucket)?,
+                prefix: prefix.trim_matches('/').to_string(),
+            })
+        } else if value.starts_with("http://") || value.starts_with("https://") {
+            Ok(DatasetSource::Http {
+                base_url: value.trim_end_matches('/').to_string(),
+            })
+        } else if let Some(path) = value.strip_prefix("file://") {
+            Ok(DatasetSource::Local {
+                dir: PathBuf::from(path),
+            })
+        } else if value.contains("://") {
+            anyhow::bail!("Unsupported dataset source {value:?}")
+        } else {
+            Ok(DatasetSource::Local {
+                dir: PathBuf::from(value),
+            })
+        }
+    }
+}
+
+impl fmt::Display for DatasetSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DatasetSource::Http { base_url } => write!(f, "{base_url}"),
+            DatasetSource::Local { dir } => write!(f, "file://{}", dir.display()),
+            DatasetSource::S3 { bucket, prefix } => write!(f, "s3://{}/{prefix}", bucket.name()),
+        }
+    }
+}